@@ -0,0 +1,212 @@
+//! Saving the random choices of a run to replay them later.
+//!
+//! Drone implementations picked with a seed and pdrs drawn from a range
+//! depend on the RNG, which may change from one version to the next. The
+//! assignment table holds what was actually chosen for every drone, and
+//! loading it turns each choice into an explicit one so no RNG is involved.
+//! Clients and servers are left out: a server mix is replayed with its seed.
+//!
+//! An implementation map is written by hand instead, to run one topology
+//! with different implementations: it only fixes the drones it lists.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+use wg_2024::network::NodeId;
+
+use crate::{
+    config::{ConfigFormat, NetworkConfig, ParseError},
+    error::NetworkInitError,
+    network_initializer::Network,
+    registry::DroneRegistry,
+};
+
+/// Implementation and pdr of every drone, sorted by id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub drone: Vec<DroneChoice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroneChoice {
+    pub id: NodeId,
+    pub implementation: String,
+    pub pdr: f32,
+}
+
+impl Assignment {
+    /// Choices `network` was built with
+    pub fn from_network(network: &Network) -> Self {
+        let mut drone: Vec<DroneChoice> = network
+            .config
+            .drone
+            .iter()
+            .map(|d| DroneChoice {
+                id: d.id,
+                // Every drone that was built has an implementation
+                implementation: network
+                    .implementations
+                    .get(&d.id)
+                    .cloned()
+                    .unwrap_or_default(),
+                pdr: d.pdr,
+            })
+            .collect();
+        drone.sort_unstable_by_key(|d| d.id);
+        Self { drone }
+    }
+
+    /// Read the table at `path`, as TOML, or as JSON or YAML for paths ending
+    /// in `.json` or `.yaml`
+    pub fn open(path: &Path) -> Result<Self, NetworkInitError> {
+        let data = fs::read_to_string(path).map_err(|source| NetworkInitError::ReadAssignment {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parsed = match ConfigFormat::from_path(path) {
+            ConfigFormat::Toml | ConfigFormat::Binary => {
+                toml::from_str(&data).map_err(ParseError::Toml)
+            }
+            ConfigFormat::Json => serde_json::from_str(&data).map_err(ParseError::Json),
+            ConfigFormat::Yaml => serde_yaml::from_str(&data).map_err(ParseError::Yaml),
+        };
+        parsed.map_err(|source| NetworkInitError::ParseAssignment {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// The table written in `format`
+    pub fn serialize(&self, format: ConfigFormat) -> String {
+        // Only scalars, no format can reject that
+        match format {
+            // Only configs have a binary form, a `.bin` path gets TOML
+            ConfigFormat::Toml | ConfigFormat::Binary => {
+                toml::to_string(self).expect("an assignment is valid TOML")
+            }
+            ConfigFormat::Json => {
+                let mut json =
+                    serde_json::to_string_pretty(self).expect("an assignment is valid JSON");
+                json.push('\n');
+                json
+            }
+            ConfigFormat::Yaml => serde_yaml::to_string(self).expect("an assignment is valid YAML"),
+        }
+    }
+
+    /// Make every choice of the table explicit in `network_config`. The
+    /// table must hold exactly the drones of the config.
+    pub fn apply(&self, network_config: &mut NetworkConfig) -> Result<(), NetworkInitError> {
+        let choices: HashMap<NodeId, &DroneChoice> = self
+            .drone
+            .iter()
+            .map(|choice| (choice.id, choice))
+            .collect();
+
+        let mut missing: Vec<NodeId> = network_config
+            .config
+            .drone
+            .iter()
+            .map(|d| d.id)
+            .filter(|id| !choices.contains_key(id))
+            .collect();
+        let mut extra: Vec<NodeId> = choices
+            .keys()
+            .copied()
+            .filter(|id| !network_config.config.drone.iter().any(|d| d.id == *id))
+            .collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            missing.sort_unstable();
+            extra.sort_unstable();
+            return Err(NetworkInitError::AssignmentMismatch { missing, extra });
+        }
+
+        for drone in &mut network_config.config.drone {
+            let choice = choices[&drone.id];
+            drone.pdr = choice.pdr;
+            network_config
+                .drone_implementations
+                .insert(drone.id, choice.implementation.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Implementation of some drones, written as `3 = "rust_roveri"`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImplementationMap(pub HashMap<NodeId, String>);
+
+impl ImplementationMap {
+    /// Read the map at `path`, as TOML, or as JSON or YAML for paths ending
+    /// in `.json` or `.yaml`
+    pub fn open(path: &Path) -> Result<Self, NetworkInitError> {
+        let data = fs::read_to_string(path).map_err(|source| NetworkInitError::ReadAssignment {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        // Keys are strings in TOML and JSON, YAML reads `3:` as a number
+        let parsed: Result<HashMap<String, String>, _> = match ConfigFormat::from_path(path) {
+            ConfigFormat::Toml | ConfigFormat::Binary => {
+                toml::from_str(&data).map_err(ParseError::Toml)
+            }
+            ConfigFormat::Json => serde_json::from_str(&data).map_err(ParseError::Json),
+            ConfigFormat::Yaml => serde_yaml::from_str::<HashMap<NodeId, String>>(&data)
+                .map(|map| map.into_iter().map(|(id, i)| (id.to_string(), i)).collect())
+                .map_err(ParseError::Yaml),
+        };
+        let parsed = parsed.map_err(|source| NetworkInitError::ParseAssignment {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut map = HashMap::with_capacity(parsed.len());
+        for (key, implementation) in parsed {
+            let id = key
+                .parse()
+                .map_err(|_| NetworkInitError::InvalidImplementationMapId {
+                    path: path.to_path_buf(),
+                    key,
+                })?;
+            map.insert(id, implementation);
+        }
+        Ok(Self(map))
+    }
+
+    /// Make the implementation of every listed drone explicit in
+    /// `network_config`, over what its entry asks for. Every id must be a
+    /// drone of the config and every name one of `registry`.
+    pub fn apply(
+        &self,
+        network_config: &mut NetworkConfig,
+        registry: &DroneRegistry,
+    ) -> Result<(), NetworkInitError> {
+        let mut unknown: Vec<NodeId> = self
+            .0
+            .keys()
+            .copied()
+            .filter(|id| !network_config.config.drone.iter().any(|d| d.id == *id))
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(NetworkInitError::ImplementationMapUnknownDrones(unknown));
+        }
+        let mut listed: Vec<(&NodeId, &String)> = self.0.iter().collect();
+        listed.sort_unstable();
+        if let Some((drone, implementation)) = listed
+            .iter()
+            .find(|(_, implementation)| !registry.contains(implementation))
+        {
+            return Err(NetworkInitError::UnknownDroneImplementation {
+                drone: **drone,
+                implementation: (*implementation).clone(),
+            });
+        }
+
+        for (drone, implementation) in listed {
+            network_config
+                .drone_implementations
+                .insert(*drone, implementation.clone());
+        }
+        Ok(())
+    }
+}
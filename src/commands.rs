@@ -0,0 +1,142 @@
+use crossbeam_channel::Sender;
+use std::collections::HashMap;
+
+use wg_2024::{controller::DroneCommand, network::NodeId};
+
+use messages::{
+    client_commands::{ChatClientCommand, MediaClientCommand},
+    server_commands::{CommunicationServerCommand, ContentServerCommand},
+};
+
+use crate::{
+    error::NetworkInitError,
+    network_initializer::Network,
+    topology::{LiveTopology, Topology},
+};
+
+/// Cloneable handle sending commands to single nodes of a running network,
+/// without going through the GUI
+#[derive(Clone)]
+pub struct NodeCommands {
+    drones: HashMap<NodeId, Sender<DroneCommand>>,
+    chat_clients: HashMap<NodeId, Sender<ChatClientCommand>>,
+    media_clients: HashMap<NodeId, Sender<MediaClientCommand>>,
+    communication_servers: HashMap<NodeId, Sender<CommunicationServerCommand>>,
+    text_servers: HashMap<NodeId, Sender<ContentServerCommand>>,
+    media_servers: HashMap<NodeId, Sender<ContentServerCommand>>,
+    topology: LiveTopology,
+}
+
+/// Keep the command sender of every `(command, packet)` pair
+fn command_senders<C, P>(senders: &HashMap<NodeId, (Sender<C>, P)>) -> HashMap<NodeId, Sender<C>> {
+    senders
+        .iter()
+        .map(|(id, (command_send, _))| (*id, command_send.clone()))
+        .collect()
+}
+
+/// Send `command` to `node` through the sender found in `senders`
+fn send<C>(
+    senders: &HashMap<NodeId, Sender<C>>,
+    node: NodeId,
+    kind: &'static str,
+    command: C,
+) -> Result<(), NetworkInitError> {
+    senders
+        .get(&node)
+        .ok_or(NetworkInitError::UnknownNode { node, kind })?
+        .send(command)
+        .map_err(|_| NetworkInitError::NodeStopped { node })
+}
+
+impl NodeCommands {
+    /// Command senders of every node of `network`
+    pub fn new(network: &Network) -> Self {
+        Self {
+            drones: command_senders(&network.drone_send),
+            chat_clients: command_senders(&network.cclient_send),
+            media_clients: command_senders(&network.mclient_send),
+            communication_servers: command_senders(&network.comm_server_send),
+            text_servers: command_senders(&network.text_server_send),
+            media_servers: command_senders(&network.media_server_send),
+            topology: network.live_topology.clone(),
+        }
+    }
+
+    /// The topology as the drone commands sent so far left it
+    pub fn topology(&self) -> Topology {
+        self.topology.current()
+    }
+
+    pub fn send_drone_command(
+        &self,
+        id: NodeId,
+        command: DroneCommand,
+    ) -> Result<(), NetworkInitError> {
+        send(&self.drones, id, "drone", command.clone())?;
+        self.topology.record(id, &command);
+        Ok(())
+    }
+
+    pub fn send_chat_client_command(
+        &self,
+        id: NodeId,
+        command: ChatClientCommand,
+    ) -> Result<(), NetworkInitError> {
+        send(&self.chat_clients, id, "chat client", command)
+    }
+
+    /// Have the chat client `client` send `text` through the communication
+    /// server `server`. Both must be nodes of the network.
+    pub fn send_chat_message(
+        &self,
+        client: NodeId,
+        server: NodeId,
+        text: String,
+    ) -> Result<(), NetworkInitError> {
+        if !self.communication_servers.contains_key(&server) {
+            return Err(NetworkInitError::UnknownNode {
+                node: server,
+                kind: "communication server",
+            });
+        }
+        self.send_chat_client_command(client, ChatClientCommand::SendMessageTo(server, text))
+    }
+
+    pub fn send_media_client_command(
+        &self,
+        id: NodeId,
+        command: MediaClientCommand,
+    ) -> Result<(), NetworkInitError> {
+        send(&self.media_clients, id, "media client", command)
+    }
+
+    pub fn send_communication_server_command(
+        &self,
+        id: NodeId,
+        command: CommunicationServerCommand,
+    ) -> Result<(), NetworkInitError> {
+        send(
+            &self.communication_servers,
+            id,
+            "communication server",
+            command,
+        )
+    }
+
+    pub fn send_text_server_command(
+        &self,
+        id: NodeId,
+        command: ContentServerCommand,
+    ) -> Result<(), NetworkInitError> {
+        send(&self.text_servers, id, "text server", command)
+    }
+
+    pub fn send_media_server_command(
+        &self,
+        id: NodeId,
+        command: ContentServerCommand,
+    ) -> Result<(), NetworkInitError> {
+        send(&self.media_servers, id, "media server", command)
+    }
+}
@@ -0,0 +1,889 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    path::Path,
+    time::Duration,
+};
+
+use wg_2024::{
+    config::{Client, Config, Drone, Server},
+    network::NodeId,
+};
+
+/// Parsed network configuration.
+///
+/// `config` is the plain `wg_2024` configuration handed to the controller and
+/// the GUI, the other fields hold the initializer-specific settings that
+/// `wg_2024::config::Config` has no room for.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    pub config: Config,
+    /// Implementation name explicitly requested by a drone entry
+    pub drone_implementations: HashMap<NodeId, String>,
+    /// Kind explicitly requested by a client entry
+    pub client_kinds: HashMap<NodeId, ClientKind>,
+    /// Kind explicitly requested by a server entry
+    pub server_kinds: HashMap<NodeId, ServerKind>,
+    /// Implementation name explicitly requested by a client entry
+    pub client_implementations: HashMap<NodeId, String>,
+    /// Implementation name explicitly requested by a server entry
+    pub server_implementations: HashMap<NodeId, String>,
+    /// Delay of an `[[edge]]` entry, keyed by `(lower id, higher id)` as
+    /// returned by [`edges`]
+    pub link_delays: HashMap<(NodeId, NodeId), Duration>,
+    /// Drone crashes to inject while the network runs, in config order
+    pub faults: Vec<Fault>,
+    /// Commands a drone entry gets once every node runs, in config order
+    pub init_commands: HashMap<NodeId, Vec<InitCommand>>,
+}
+
+// `wg_2024::config::Config` has no `Default`
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                drone: Vec::new(),
+                client: Vec::new(),
+                server: Vec::new(),
+            },
+            drone_implementations: HashMap::new(),
+            client_kinds: HashMap::new(),
+            server_kinds: HashMap::new(),
+            client_implementations: HashMap::new(),
+            server_implementations: HashMap::new(),
+            link_delays: HashMap::new(),
+            faults: Vec::new(),
+            init_commands: HashMap::new(),
+        }
+    }
+}
+
+/// Crash of `drone` at `crash_at` after the network started, and its
+/// rebuild at `restore_at` if given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub drone: NodeId,
+    pub crash_at: Duration,
+    pub restore_at: Option<Duration>,
+}
+
+/// Drone command of an `init_commands` entry, written as
+/// `{ command = "set_pdr", pdr = 0.5 }`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "command", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InitCommand {
+    /// `SetPacketDropRate`
+    SetPdr { pdr: f32 },
+    /// `RemoveSender` of `node`
+    RemoveSender { node: NodeId },
+    /// `AddSender` of `node`, with its packet sender
+    AddSender { node: NodeId },
+    /// `Crash`
+    Crash,
+}
+
+impl InitCommand {
+    /// Node the command adds or removes, if any
+    pub fn node(&self) -> Option<NodeId> {
+        match self {
+            Self::RemoveSender { node } | Self::AddSender { node } => Some(*node),
+            Self::SetPdr { .. } | Self::Crash => None,
+        }
+    }
+}
+
+/// Which client is built for a client entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientKind {
+    /// `ChatClient`
+    Chat,
+    /// `MediaClient`
+    Media,
+}
+
+/// Which server is built for a server entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerKind {
+    /// `ContentServer` serving text files
+    Text,
+    /// `ContentServer` serving media files
+    Media,
+    /// `CommunicationServer`
+    Communication,
+}
+
+impl fmt::Display for ClientKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Chat => write!(f, "chat"),
+            Self::Media => write!(f, "media"),
+        }
+    }
+}
+
+impl fmt::Display for ServerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Media => write!(f, "media"),
+            Self::Communication => write!(f, "communication"),
+        }
+    }
+}
+
+/// The three sections of the config a node can be defined in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Drone,
+    Client,
+    Server,
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Drone => write!(f, "drone"),
+            Self::Client => write!(f, "client"),
+            Self::Server => write!(f, "server"),
+        }
+    }
+}
+
+/// Every node of `config` with its kind and neighbor list, drones first,
+/// then clients, then servers, each in file order
+pub fn nodes(config: &Config) -> impl Iterator<Item = (NodeKind, NodeId, &[NodeId])> {
+    let drones = config
+        .drone
+        .iter()
+        .map(|d| (NodeKind::Drone, d.id, d.connected_node_ids.as_slice()));
+    let clients = config
+        .client
+        .iter()
+        .map(|c| (NodeKind::Client, c.id, c.connected_drone_ids.as_slice()));
+    let servers = config
+        .server
+        .iter()
+        .map(|s| (NodeKind::Server, s.id, s.connected_drone_ids.as_slice()));
+
+    drones.chain(clients).chain(servers)
+}
+
+/// Every edge of `config` once, as `(lower id, higher id)`, even though both
+/// of its ends list it
+pub fn edges(config: &Config) -> BTreeSet<(NodeId, NodeId)> {
+    nodes(config)
+        .flat_map(|(_, id, neighbors)| neighbors.iter().map(move |n| (id.min(*n), id.max(*n))))
+        .collect()
+}
+
+// Unknown keys are rejected rather than ignored, so a misspelled one like
+// `connected_drones_ids` is reported with its line instead of leaving the
+// node without neighbors. Serialized only to write the binary format,
+// compared only in tests.
+#[derive(Deserialize, Serialize, Default)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    drone: Vec<RawDrone>,
+    #[serde(default)]
+    client: Vec<RawClient>,
+    #[serde(default)]
+    server: Vec<RawServer>,
+    /// Topology as a flat edge list, added to the per-node neighbor lists
+    #[serde(default)]
+    edge: Vec<RawEdge>,
+    #[serde(default)]
+    fault: Vec<RawFault>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(deny_unknown_fields)]
+struct RawFault {
+    drone: NodeId,
+    crash_at_ms: u64,
+    restore_at_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(deny_unknown_fields)]
+struct RawEdge {
+    a: NodeId,
+    b: NodeId,
+    /// Milliseconds every packet takes to cross the edge
+    delay_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(deny_unknown_fields)]
+struct RawDrone {
+    id: NodeId,
+    #[serde(default)]
+    connected_node_ids: Vec<NodeId>,
+    pdr: f32,
+    implementation: Option<String>,
+    #[serde(default)]
+    init_commands: Vec<InitCommand>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(deny_unknown_fields)]
+struct RawClient {
+    id: NodeId,
+    #[serde(default)]
+    connected_drone_ids: Vec<NodeId>,
+    kind: Option<ClientKind>,
+    implementation: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(deny_unknown_fields)]
+struct RawServer {
+    id: NodeId,
+    #[serde(default)]
+    connected_drone_ids: Vec<NodeId>,
+    kind: Option<ServerKind>,
+    implementation: Option<String>,
+}
+
+/// Neighbors of every node given by an edge list, both ends of an edge get
+/// the other one
+fn edge_neighbors(edges: &[RawEdge]) -> HashMap<NodeId, Vec<NodeId>> {
+    let mut neighbors = HashMap::<NodeId, BTreeSet<NodeId>>::new();
+    for edge in edges {
+        neighbors.entry(edge.a).or_default().insert(edge.b);
+        neighbors.entry(edge.b).or_default().insert(edge.a);
+    }
+    neighbors
+        .into_iter()
+        .map(|(id, neighbors)| (id, neighbors.into_iter().collect()))
+        .collect()
+}
+
+impl From<RawConfig> for NetworkConfig {
+    fn from(raw: RawConfig) -> Self {
+        // Edges add to the per-node lists, so fragments written in either
+        // style can be merged
+        let edge_neighbors = edge_neighbors(&raw.edge);
+        let link_delays = raw
+            .edge
+            .iter()
+            .filter_map(|edge| {
+                let delay = Duration::from_millis(edge.delay_ms?);
+                Some(((edge.a.min(edge.b), edge.a.max(edge.b)), delay))
+            })
+            .collect();
+        let neighbors_of = |id: NodeId, mut listed: Vec<NodeId>| {
+            for neighbor in edge_neighbors.get(&id).into_iter().flatten() {
+                if !listed.contains(neighbor) {
+                    listed.push(*neighbor);
+                }
+            }
+            listed
+        };
+
+        let mut drone_implementations = HashMap::new();
+        let mut init_commands = HashMap::new();
+        let mut drones = Vec::with_capacity(raw.drone.len());
+
+        for drone in raw.drone {
+            if let Some(implementation) = drone.implementation {
+                drone_implementations.insert(drone.id, implementation);
+            }
+            if !drone.init_commands.is_empty() {
+                init_commands.insert(drone.id, drone.init_commands);
+            }
+            drones.push(Drone {
+                id: drone.id,
+                connected_node_ids: neighbors_of(drone.id, drone.connected_node_ids),
+                pdr: drone.pdr,
+            });
+        }
+
+        let mut client_kinds = HashMap::new();
+        let mut client_implementations = HashMap::new();
+        let mut clients = Vec::with_capacity(raw.client.len());
+
+        for client in raw.client {
+            if let Some(kind) = client.kind {
+                client_kinds.insert(client.id, kind);
+            }
+            if let Some(implementation) = client.implementation {
+                client_implementations.insert(client.id, implementation);
+            }
+            clients.push(Client {
+                id: client.id,
+                connected_drone_ids: neighbors_of(client.id, client.connected_drone_ids),
+            });
+        }
+
+        let mut server_kinds = HashMap::new();
+        let mut server_implementations = HashMap::new();
+        let mut servers = Vec::with_capacity(raw.server.len());
+
+        for server in raw.server {
+            if let Some(kind) = server.kind {
+                server_kinds.insert(server.id, kind);
+            }
+            if let Some(implementation) = server.implementation {
+                server_implementations.insert(server.id, implementation);
+            }
+            servers.push(Server {
+                id: server.id,
+                connected_drone_ids: neighbors_of(server.id, server.connected_drone_ids),
+            });
+        }
+
+        Self {
+            config: Config {
+                drone: drones,
+                client: clients,
+                server: servers,
+            },
+            drone_implementations,
+            client_kinds,
+            server_kinds,
+            client_implementations,
+            server_implementations,
+            link_delays,
+            faults: raw
+                .fault
+                .iter()
+                .map(|fault| Fault {
+                    drone: fault.drone,
+                    crash_at: Duration::from_millis(fault.crash_at_ms),
+                    restore_at: fault.restore_at_ms.map(Duration::from_millis),
+                })
+                .collect(),
+            init_commands,
+        }
+    }
+}
+
+/// Builds a [`NetworkConfig`] in code, without a config file.
+///
+/// Neighbor lists are taken as given, both ends of an edge must list it as
+/// in a config file.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    network_config: NetworkConfig,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_drone(&mut self, id: NodeId, pdr: f32, neighbors: &[NodeId]) -> &mut Self {
+        self.network_config.config.drone.push(Drone {
+            id,
+            connected_node_ids: neighbors.to_vec(),
+            pdr,
+        });
+        self
+    }
+
+    pub fn add_client(&mut self, id: NodeId, neighbors: &[NodeId]) -> &mut Self {
+        self.network_config.config.client.push(Client {
+            id,
+            connected_drone_ids: neighbors.to_vec(),
+        });
+        self
+    }
+
+    pub fn add_server(&mut self, id: NodeId, neighbors: &[NodeId]) -> &mut Self {
+        self.network_config.config.server.push(Server {
+            id,
+            connected_drone_ids: neighbors.to_vec(),
+        });
+        self
+    }
+
+    /// Same as `implementation = "..."` on the drone entry `id`
+    pub fn drone_implementation(&mut self, id: NodeId, implementation: &str) -> &mut Self {
+        self.network_config
+            .drone_implementations
+            .insert(id, implementation.to_string());
+        self
+    }
+
+    /// Same as `kind = "..."` on the client entry `id`
+    pub fn client_kind(&mut self, id: NodeId, kind: ClientKind) -> &mut Self {
+        self.network_config.client_kinds.insert(id, kind);
+        self
+    }
+
+    /// Same as `kind = "..."` on the server entry `id`
+    pub fn server_kind(&mut self, id: NodeId, kind: ServerKind) -> &mut Self {
+        self.network_config.server_kinds.insert(id, kind);
+        self
+    }
+
+    /// Same as `implementation = "..."` on the client entry `id`
+    pub fn client_implementation(&mut self, id: NodeId, implementation: &str) -> &mut Self {
+        self.network_config
+            .client_implementations
+            .insert(id, implementation.to_string());
+        self
+    }
+
+    /// Same as `implementation = "..."` on the server entry `id`
+    pub fn server_implementation(&mut self, id: NodeId, implementation: &str) -> &mut Self {
+        self.network_config
+            .server_implementations
+            .insert(id, implementation.to_string());
+        self
+    }
+
+    /// Same as `delay_ms` on an `[[edge]]` entry between `a` and `b`, which
+    /// must still list each other as neighbors
+    pub fn link_delay(&mut self, a: NodeId, b: NodeId, delay: Duration) -> &mut Self {
+        self.network_config
+            .link_delays
+            .insert((a.min(b), a.max(b)), delay);
+        self
+    }
+
+    /// Same as a `[[fault]]` entry crashing `drone` at `crash_at`, and
+    /// rebuilding it at `restore_at` if given
+    pub fn fault(
+        &mut self,
+        drone: NodeId,
+        crash_at: Duration,
+        restore_at: Option<Duration>,
+    ) -> &mut Self {
+        self.network_config.faults.push(Fault {
+            drone,
+            crash_at,
+            restore_at,
+        });
+        self
+    }
+
+    /// Same as an `init_commands` entry of `drone`
+    pub fn init_command(&mut self, drone: NodeId, command: InitCommand) -> &mut Self {
+        self.network_config
+            .init_commands
+            .entry(drone)
+            .or_default()
+            .push(command);
+        self
+    }
+
+    pub fn build(&self) -> NetworkConfig {
+        self.network_config.clone()
+    }
+}
+
+/// Languages a config file can be written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+    Yaml,
+    /// MessagePack with named fields, the layout of the TOML one, for
+    /// topologies too large to parse quickly as text. See [`to_binary`].
+    Binary,
+}
+
+impl ConfigFormat {
+    /// Format matching the extension of `path`, TOML when it's not a known one
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            Some(ext) if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") => {
+                Self::Yaml
+            }
+            Some(ext) if ext.eq_ignore_ascii_case("bin") => Self::Binary,
+            _ => Self::Toml,
+        }
+    }
+}
+
+/// Why a config file couldn't be parsed
+#[derive(Debug)]
+pub enum ParseError {
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    Yaml(serde_yaml::Error),
+    Binary(rmp_serde::decode::Error),
+    /// A text config that isn't valid UTF-8
+    Utf8(std::str::Utf8Error),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toml(err) => write!(f, "TOML: {err}"),
+            Self::Json(err) => write!(f, "JSON: {err}"),
+            Self::Yaml(err) => write!(f, "YAML: {err}"),
+            Self::Binary(err) => write!(f, "binary config: {err}"),
+            Self::Utf8(err) => write!(f, "text config: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Toml(err) => Some(err),
+            Self::Json(err) => Some(err),
+            Self::Yaml(err) => Some(err),
+            Self::Binary(err) => Some(err),
+            Self::Utf8(err) => Some(err),
+        }
+    }
+}
+
+impl ParseError {
+    /// Where parsing `data`, the config this error comes from, failed.
+    /// `None` for a binary config and for an error without a position.
+    pub fn locate(&self, data: &[u8]) -> Option<ErrorLocation> {
+        match self {
+            Self::Toml(err) => ErrorLocation::at_offset(data, err.span()?.start),
+            Self::Yaml(err) => ErrorLocation::at_offset(data, err.location()?.index()),
+            Self::Utf8(err) => ErrorLocation::at_offset(data, err.valid_up_to()),
+            // Only the line and column are known
+            Self::Json(err) if err.line() > 0 => {
+                let text = String::from_utf8_lossy(data);
+                let snippet = text.lines().nth(err.line() - 1).unwrap_or_default();
+                Some(ErrorLocation {
+                    line: err.line(),
+                    column: err.column().max(1),
+                    snippet: snippet.to_string(),
+                })
+            }
+            Self::Json(_) | Self::Binary(_) => None,
+        }
+    }
+
+    /// The error without the position its own message may give, to be shown
+    /// next to an [`ErrorLocation`]
+    pub fn message(&self) -> String {
+        match self {
+            Self::Toml(err) => format!("TOML: {}", err.message()),
+            Self::Json(err) => {
+                let suffix = format!(" at line {} column {}", err.line(), err.column());
+                let message = err.to_string();
+                format!(
+                    "JSON: {}",
+                    message.strip_suffix(&suffix).unwrap_or(&message)
+                )
+            }
+            Self::Yaml(err) => {
+                let message = err.to_string();
+                let message = match err.location() {
+                    Some(at) => {
+                        let suffix = format!(" at line {} column {}", at.line(), at.column());
+                        message
+                            .strip_suffix(&suffix)
+                            .unwrap_or(&message)
+                            .to_string()
+                    }
+                    None => message,
+                };
+                format!("YAML: {message}")
+            }
+            Self::Binary(_) | Self::Utf8(_) => self.to_string(),
+        }
+    }
+}
+
+/// Line and column, both counted from 1, where a text config stopped
+/// parsing, with the text of that line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorLocation {
+    pub line: usize,
+    pub column: usize,
+    pub snippet: String,
+}
+
+impl ErrorLocation {
+    /// Location of the byte at `offset` in `data`, `None` past its end
+    fn at_offset(data: &[u8], offset: usize) -> Option<Self> {
+        let (before, after) = (data.get(..offset)?, &data[offset..]);
+        let line_start = before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let line_end = after
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(data.len(), |newline| offset + newline);
+        let column = String::from_utf8_lossy(&before[line_start..])
+            .chars()
+            .count()
+            + 1;
+        let snippet = String::from_utf8_lossy(&data[line_start..line_end]);
+        Some(Self {
+            line: before.iter().filter(|byte| **byte == b'\n').count() + 1,
+            column,
+            snippet: snippet.trim_end_matches('\r').to_string(),
+        })
+    }
+}
+
+impl fmt::Display for ErrorLocation {
+    /// The line with its number, then a caret under the column
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = self.line.to_string();
+        // Tabs kept so the caret lines up with the snippet
+        let indent: String = self
+            .snippet
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{number} | {}", self.snippet)?;
+        write!(f, "{:width$} | {indent}^", "", width = number.len())
+    }
+}
+
+/// Why the `${VAR}` references of a config couldn't be replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpandError {
+    /// `${name}` has no default and `name` isn't set
+    Undefined(String),
+    /// A `${` is never closed
+    Unterminated,
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined(name) => {
+                write!(
+                    f,
+                    "environment variable {name} is not set and has no default"
+                )
+            }
+            Self::Unterminated => f.write_str("a ${ is never closed by }"),
+        }
+    }
+}
+
+impl std::error::Error for ExpandError {}
+
+/// Replace every `${VAR}` of `data` with the value of the environment
+/// variable `VAR`, and every `${VAR:-default}` with `default` when `VAR` is
+/// unset or empty. The replacement is plain text, done before any parsing,
+/// except for a reference making up a whole double-quoted string: its quotes
+/// are dropped when the value is a number or a boolean, so
+/// `pdr = "${DEFAULT_PDR}"` gets a float like `pdr = ${DEFAULT_PDR}` does,
+/// and `implementation = "${DRONE}"` stays a string.
+pub fn expand_env(data: &str) -> Result<String, ExpandError> {
+    let mut expanded = String::with_capacity(data.len());
+    let mut rest = data;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference.find('}').ok_or(ExpandError::Unterminated)?;
+        let value = match reference[..end].split_once(":-") {
+            Some((name, default)) => std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| default.to_string()),
+            None => {
+                let name = &reference[..end];
+                std::env::var(name).map_err(|_| ExpandError::Undefined(name.to_string()))?
+            }
+        };
+        rest = &reference[end + 1..];
+        if expanded.ends_with('"') && rest.starts_with('"') && is_scalar(&value) {
+            expanded.pop();
+            rest = &rest[1..];
+        }
+        expanded.push_str(&value);
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Whether `value` reads as a number or a boolean once unquoted
+fn is_scalar(value: &str) -> bool {
+    value == "true" || value == "false" || value.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// Parse the content of a config file, in the format matching its extension
+pub fn parse_config(
+    path: &Path,
+    data: &(impl AsRef<[u8]> + ?Sized),
+) -> Result<NetworkConfig, ParseError> {
+    parse(ConfigFormat::from_path(path), data)
+}
+
+/// Parse the content of a config file written in `format`
+pub fn parse(
+    format: ConfigFormat,
+    data: &(impl AsRef<[u8]> + ?Sized),
+) -> Result<NetworkConfig, ParseError> {
+    Ok(parse_raw(format, data.as_ref())?.into())
+}
+
+/// The config `data`, written in `format`, in the binary format. Nothing is
+/// resolved on the way: edges, kinds and faults come out as they were
+/// written, and the file parses to the same config.
+pub fn to_binary(
+    format: ConfigFormat,
+    data: &(impl AsRef<[u8]> + ?Sized),
+) -> Result<Vec<u8>, ParseError> {
+    let raw = parse_raw(format, data.as_ref())?;
+    // Only structs, strings and numbers, nothing MessagePack can reject
+    Ok(rmp_serde::to_vec_named(&raw).expect("a config is valid MessagePack"))
+}
+
+/// Parse several config files, each written in its own format, as a single
+/// one: their drones, clients, servers and edges are put together before
+/// anything else, so an edge can connect nodes of different files
+pub fn parse_fragments<'a, D: AsRef<[u8]> + ?Sized + 'a>(
+    fragments: impl IntoIterator<Item = (ConfigFormat, &'a D)>,
+) -> Result<NetworkConfig, ParseError> {
+    let mut merged = RawConfig::default();
+    for (format, data) in fragments {
+        let raw = parse_raw(format, data.as_ref())?;
+        merged.drone.extend(raw.drone);
+        merged.client.extend(raw.client);
+        merged.server.extend(raw.server);
+        merged.edge.extend(raw.edge);
+        merged.fault.extend(raw.fault);
+    }
+    Ok(merged.into())
+}
+
+fn parse_raw(format: ConfigFormat, data: &[u8]) -> Result<RawConfig, ParseError> {
+    match format {
+        ConfigFormat::Toml => {
+            let data = std::str::from_utf8(data).map_err(ParseError::Utf8)?;
+            toml::from_str(data).map_err(ParseError::Toml)
+        }
+        ConfigFormat::Json => serde_json::from_slice(data).map_err(ParseError::Json),
+        ConfigFormat::Yaml => serde_yaml::from_slice(data).map_err(ParseError::Yaml),
+        ConfigFormat::Binary => rmp_serde::from_slice(data).map_err(ParseError::Binary),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_and_yaml_parse_to_the_same_config() {
+        let toml = r#"
+[[drone]]
+id = 1
+connected_node_ids = [2, 3]
+pdr = 0.1
+implementation = "rustastic"
+init_commands = [{ command = "set_pdr", pdr = 0.5 }]
+
+[[client]]
+id = 2
+connected_drone_ids = [1]
+kind = "media"
+
+[[server]]
+id = 3
+connected_drone_ids = [1]
+kind = "communication"
+
+[[edge]]
+a = 1
+b = 2
+delay_ms = 20
+
+[[fault]]
+drone = 1
+crash_at_ms = 1000
+restore_at_ms = 2000
+"#;
+        let yaml = r#"
+drone:
+  - id: 1
+    connected_node_ids: [2, 3]
+    pdr: 0.1
+    implementation: rustastic
+    init_commands:
+      - command: set_pdr
+        pdr: 0.5
+client:
+  - id: 2
+    connected_drone_ids: [1]
+    kind: media
+server:
+  - id: 3
+    connected_drone_ids: [1]
+    kind: communication
+edge:
+  - a: 1
+    b: 2
+    delay_ms: 20
+fault:
+  - drone: 1
+    crash_at_ms: 1000
+    restore_at_ms: 2000
+"#;
+        assert_eq!(
+            parse_raw(ConfigFormat::Toml, toml.as_bytes()).unwrap(),
+            parse_raw(ConfigFormat::Yaml, yaml.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn merged_fragments_keep_inline_neighbors_and_edges() {
+        let endpoints = r#"
+[[drone]]
+id = 1
+connected_node_ids = [3]
+pdr = 0.0
+
+[[client]]
+id = 3
+connected_drone_ids = [1]
+"#;
+        let backbone = r#"
+[[drone]]
+id = 2
+pdr = 0.0
+
+[[edge]]
+a = 1
+b = 2
+"#;
+        let network_config = parse_fragments([
+            (ConfigFormat::Toml, endpoints),
+            (ConfigFormat::Toml, backbone),
+        ])
+        .unwrap();
+
+        let neighbors: HashMap<NodeId, Vec<NodeId>> = nodes(&network_config.config)
+            .map(|(_, id, neighbors)| (id, neighbors.to_vec()))
+            .collect();
+        assert_eq!(
+            neighbors,
+            HashMap::from([(1, vec![3, 2]), (2, vec![1]), (3, vec![1])])
+        );
+    }
+
+    #[test]
+    fn expand_env_unquotes_whole_numeric_strings() {
+        let expanded = expand_env(concat!(
+            "pdr = \"${NI_TEST_UNSET:-0.25}\"\n",
+            "implementation = \"${NI_TEST_UNSET:-rustastic}\"\n",
+            "name = \"drone-${NI_TEST_UNSET:-7}\"",
+        ))
+        .unwrap();
+        assert_eq!(
+            expanded,
+            "pdr = 0.25\nimplementation = \"rustastic\"\nname = \"drone-7\""
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! The simulation controller a network runs with.
+//!
+//! The controller is built last, from channels that only exist once every
+//! node is wired, so a custom one is given as a factory rather than as a
+//! built value: it receives every channel in a [`ControllerChannels`] and
+//! returns anything implementing [`Controller`]. Without one, the Rustastic
+//! `SimulationController` is used.
+
+use crossbeam_channel::{Receiver, Sender};
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use wg_2024::{
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+    packet::Packet,
+};
+
+use messages::{
+    client_commands::{ChatClientCommand, ChatClientEvent, MediaClientCommand, MediaClientEvent},
+    gui_commands::{GUICommands, GUIEvents},
+    server_commands::{
+        CommunicationServerCommand, CommunicationServerEvent, ContentServerCommand,
+        ContentServerEvent,
+    },
+};
+use simulation_controller::SimulationController;
+
+/// Loop driving the network, run on its own thread or on the main one
+pub trait Controller: Send {
+    fn run(&mut self);
+}
+
+impl Controller for SimulationController {
+    fn run(&mut self) {
+        SimulationController::run(self);
+    }
+}
+
+/// Every channel a controller is given, in the order
+/// `SimulationController::new` takes them
+pub struct ControllerChannels {
+    pub drone_send: HashMap<NodeId, (Sender<DroneCommand>, Sender<Packet>)>,
+    pub drone_event_recv: Receiver<DroneEvent>,
+    /// Neighbors of every node
+    pub topology: HashMap<NodeId, Vec<NodeId>>,
+    /// Sender the drones report on, for drones the controller adds
+    pub drone_event_send: Sender<DroneEvent>,
+    pub gui_event_send: Sender<GUIEvents>,
+    pub gui_command_recv: Receiver<GUICommands>,
+    pub cclient_send: HashMap<NodeId, (Sender<ChatClientCommand>, Sender<Packet>)>,
+    pub cclient_event_recv: Receiver<ChatClientEvent>,
+    pub mclient_send: HashMap<NodeId, (Sender<MediaClientCommand>, Sender<Packet>)>,
+    pub mclient_event_recv: Receiver<MediaClientEvent>,
+    pub comm_server_send: HashMap<NodeId, (Sender<CommunicationServerCommand>, Sender<Packet>)>,
+    pub comm_server_event_recv: Receiver<CommunicationServerEvent>,
+    pub text_server_send: HashMap<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>,
+    pub text_server_event_recv: Receiver<ContentServerEvent>,
+    pub media_server_send: HashMap<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>,
+    pub media_server_event_recv: Receiver<ContentServerEvent>,
+}
+
+/// Function building a controller on top of the channels of a network
+pub type ControllerFactoryFn = dyn Fn(ControllerChannels) -> Box<dyn Controller> + Send + Sync;
+
+/// Cloneable controller factory, the Rustastic `SimulationController` by
+/// default
+#[derive(Clone)]
+pub struct ControllerFactory(Arc<ControllerFactoryFn>);
+
+impl ControllerFactory {
+    pub fn new(
+        factory: impl Fn(ControllerChannels) -> Box<dyn Controller> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(factory))
+    }
+
+    pub fn build(&self, channels: ControllerChannels) -> Box<dyn Controller> {
+        (self.0)(channels)
+    }
+}
+
+impl Default for ControllerFactory {
+    fn default() -> Self {
+        Self::new(|channels| Box::new(simulation_controller(channels)))
+    }
+}
+
+impl fmt::Debug for ControllerFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ControllerFactory")
+    }
+}
+
+/// The Rustastic controller, wired with `channels`
+pub fn simulation_controller(channels: ControllerChannels) -> SimulationController {
+    SimulationController::new(
+        channels.drone_send,
+        channels.drone_event_recv,
+        channels.topology,
+        channels.drone_event_send,
+        channels.gui_event_send,
+        channels.gui_command_recv,
+        channels.cclient_send,
+        channels.cclient_event_recv,
+        channels.mclient_send,
+        channels.mclient_event_recv,
+        channels.comm_server_send,
+        channels.comm_server_event_recv,
+        channels.text_server_send,
+        channels.text_server_event_recv,
+        channels.media_server_send,
+        channels.media_server_event_recv,
+    )
+}
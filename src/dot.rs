@@ -0,0 +1,57 @@
+use std::fmt::Write;
+
+use crate::{
+    config::{self, ClientKind, NetworkConfig, ServerKind},
+    network_initializer::{classify_clients, classify_servers},
+};
+
+/// Render the configured topology as a Graphviz graph.
+///
+/// Nodes are shaped and colored by type, every edge appears once even though
+/// both of its ends list it.
+pub fn topology_to_dot(network_config: &NetworkConfig) -> String {
+    let config = &network_config.config;
+    let mut dot = String::from("graph network {\n    node [style=filled];\n\n");
+
+    // `write!` on a String can't fail
+    for drone in &config.drone {
+        let _ = writeln!(
+            dot,
+            "    {} [label=\"Drone {}\\npdr {}\", shape=ellipse, fillcolor=lightblue];",
+            drone.id, drone.id, drone.pdr
+        );
+    }
+    let client_kinds = classify_clients(network_config);
+    for client in &config.client {
+        let (label, color) = match client_kinds[&client.id] {
+            ClientKind::Chat => ("Chat Client", "palegreen"),
+            ClientKind::Media => ("Media Client", "darkseagreen"),
+        };
+        let _ = writeln!(
+            dot,
+            "    {} [label=\"{label} {}\", shape=box, fillcolor={color}];",
+            client.id, client.id
+        );
+    }
+    let server_kinds = classify_servers(network_config);
+    for server in &config.server {
+        let (label, color) = match server_kinds[&server.id] {
+            ServerKind::Text => ("Text Server", "khaki"),
+            ServerKind::Media => ("Media Server", "orange"),
+            ServerKind::Communication => ("Communication Server", "salmon"),
+        };
+        let _ = writeln!(
+            dot,
+            "    {} [label=\"{label} {}\", shape=box3d, fillcolor={color}];",
+            server.id, server.id
+        );
+    }
+
+    dot.push('\n');
+    for (a, b) in config::edges(config) {
+        let _ = writeln!(dot, "    {a} -- {b};");
+    }
+    dot.push_str("}\n");
+
+    dot
+}
@@ -0,0 +1,319 @@
+use colored::Colorize;
+use log::error;
+use std::{any::Any, fmt, io, path::PathBuf};
+
+use wg_2024::network::NodeId;
+
+use crate::{
+    config::{ErrorLocation, ExpandError, ParseError, ServerKind},
+    validation::ValidationError,
+};
+
+/// Everything that can go wrong while building and launching the network
+#[derive(Debug)]
+pub enum NetworkInitError {
+    /// The config file could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// An output file could not be written
+    Write { path: PathBuf, source: io::Error },
+    /// A `${VAR}` reference of the config file can't be replaced
+    ExpandEnv { path: PathBuf, source: ExpandError },
+    /// The config file is not valid TOML/JSON/YAML or does not match the
+    /// expected layout. `location` is where it stopped, for a text config.
+    Parse {
+        path: PathBuf,
+        location: Option<ErrorLocation>,
+        source: ParseError,
+    },
+    /// A channel that should have been created for a node is missing
+    MissingChannel { node: NodeId, channel: &'static str },
+    /// There's no drone implementation for the drone at the given position
+    NoFactoryForDrone { index: usize, drone: NodeId },
+    /// The constructor of a drone implementation panicked, `implementation`
+    /// is the drone type
+    DroneConstruction {
+        id: NodeId,
+        implementation: String,
+        payload: String,
+    },
+    /// A drone asked for an implementation that doesn't exist
+    UnknownDroneImplementation {
+        drone: NodeId,
+        implementation: String,
+    },
+    /// The implementation forced on every drone doesn't exist, `available`
+    /// lists the ones that do
+    UnknownForcedImplementation {
+        implementation: String,
+        available: Vec<String>,
+    },
+    /// A client or server asked for an implementation that doesn't exist for
+    /// its kind, e.g. "chat client"
+    UnknownNodeImplementation {
+        node: NodeId,
+        kind: String,
+        implementation: String,
+    },
+    /// The config parsed fine but describes an invalid network
+    InvalidConfig(Vec<ValidationError>),
+    /// The packet drop rate forced on every drone is out of range
+    InvalidPdrOverride(f32),
+    /// The pdr range asked for isn't `0.0 <= min <= max <= 1.0`
+    InvalidPdrRange { min: f32, max: f32 },
+    /// The server mix has a negative or non-finite weight, or no positive one
+    InvalidServerMix {
+        communication: f64,
+        text: f64,
+        media: f64,
+    },
+    /// No server kind is allowed although the config has servers
+    NoServerKinds,
+    /// The config gives `server` a kind left out of the allowed ones
+    ExcludedServerKind { server: NodeId, kind: ServerKind },
+    /// The running network got no answer from these servers to the probes
+    /// of these clients, as `(client, server)` pairs
+    Unreachable(Vec<(NodeId, NodeId)>),
+    /// The metrics server couldn't listen on its port
+    Metrics { port: u16, source: io::Error },
+    /// The OS refused to create a thread
+    Spawn { thread: String, source: io::Error },
+    /// The GUI panicked while being built, or its window couldn't be opened
+    Gui(String),
+    /// Some threads of the network panicked
+    NodesPanicked(Vec<NodePanic>),
+    /// Some threads were still running once the join timeout expired
+    JoinTimeout(Vec<String>),
+    /// A command was sent to a node that isn't in the network, `kind` is the
+    /// node type it was meant for
+    UnknownNode { node: NodeId, kind: &'static str },
+    /// A command was sent to a node that stopped running
+    NodeStopped { node: NodeId },
+    /// Running the network takes more threads than `--max-threads` allows
+    TooManyThreads { needed: usize, max: usize },
+    /// The assignment table could not be read
+    ReadAssignment { path: PathBuf, source: io::Error },
+    /// The assignment table is not a valid table
+    ParseAssignment { path: PathBuf, source: ParseError },
+    /// The assignment table doesn't hold exactly the drones of the config:
+    /// `missing` are in the config only, `extra` in the table only
+    AssignmentMismatch {
+        missing: Vec<NodeId>,
+        extra: Vec<NodeId>,
+    },
+    /// Repeated runs need a duration to end each run
+    RepeatWithoutDuration,
+    /// A key of the implementation map isn't a node id
+    InvalidImplementationMapId { path: PathBuf, key: String },
+    /// The implementation map lists these ids, which aren't drones of the
+    /// config
+    ImplementationMapUnknownDrones(Vec<NodeId>),
+}
+
+/// A thread of the network that panicked
+#[derive(Debug, Clone)]
+pub struct NodePanic {
+    /// Which node ran on the thread, e.g. "drone 4"
+    pub node: String,
+    /// The panic message
+    pub payload: String,
+}
+
+impl NodePanic {
+    /// Describe the panic of `node` and log it right away
+    pub fn new(node: String, payload: &(dyn Any + Send)) -> Self {
+        let payload = panic_message(payload);
+        error!(
+            "[ {} ] {node} panicked: {payload}",
+            "Network Initializer".red()
+        );
+        Self { node, payload }
+    }
+}
+
+/// Message a panic was raised with
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+impl fmt::Display for NetworkInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "Unable to read config file {}: {source}", path.display())
+            }
+            Self::Write { path, source } => {
+                write!(f, "Unable to write {}: {source}", path.display())
+            }
+            Self::Parse {
+                path,
+                location: Some(location),
+                source,
+            } => write!(
+                f,
+                "Unable to parse {}, line {}, column {}: {}\n{location}",
+                path.display(),
+                location.line,
+                location.column,
+                source.message()
+            ),
+            Self::Parse {
+                path,
+                location: None,
+                source,
+            } => write!(f, "Unable to parse {}: {source}", path.display()),
+            Self::MissingChannel { node, channel } => {
+                write!(f, "{channel} not found for node {node}")
+            }
+            Self::NoFactoryForDrone { index, drone } => {
+                write!(
+                    f,
+                    "No factory defined for [ Drone {drone} ] at position {index}"
+                )
+            }
+            Self::DroneConstruction {
+                id,
+                implementation,
+                payload,
+            } => write!(
+                f,
+                "Unable to build [ Drone {id} ], {implementation}::new panicked: {payload}"
+            ),
+            Self::ExpandEnv { path, source } => {
+                write!(f, "Unable to expand {}: {source}", path.display())
+            }
+            Self::UnknownDroneImplementation {
+                drone,
+                implementation,
+            } => write!(
+                f,
+                "Unknown implementation \"{implementation}\" for [ Drone {drone} ]"
+            ),
+            Self::UnknownForcedImplementation {
+                implementation,
+                available,
+            } => write!(
+                f,
+                "Unknown drone implementation \"{implementation}\", expected one of: {}",
+                available.join(", ")
+            ),
+            Self::UnknownNodeImplementation {
+                node,
+                kind,
+                implementation,
+            } => write!(
+                f,
+                "Unknown {kind} implementation \"{implementation}\" for node {node}"
+            ),
+            Self::InvalidConfig(errors) => {
+                write!(f, "Invalid config:")?;
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+            Self::InvalidPdrOverride(pdr) => {
+                write!(f, "PDR override {pdr} is not a value in [0.0, 1.0]")
+            }
+            Self::InvalidPdrRange { min, max } => {
+                write!(f, "PDR range {min}:{max} is not a range within [0.0, 1.0]")
+            }
+            Self::InvalidServerMix {
+                communication,
+                text,
+                media,
+            } => write!(
+                f,
+                "Server mix {communication}:{text}:{media} needs non-negative weights with a positive sum"
+            ),
+            Self::NoServerKinds => f.write_str("The config has servers but no server kind is allowed"),
+            Self::ExcludedServerKind { server, kind } => {
+                write!(f, "Server {server} is a {kind} server, a kind that isn't allowed")
+            }
+            Self::Unreachable(pairs) => {
+                write!(f, "No answer from the servers of the client -> server pairs {pairs:?}")
+            }
+            Self::Metrics { port, source } => {
+                write!(f, "Unable to serve metrics on port {port}: {source}")
+            }
+            Self::Spawn { thread, source } => {
+                write!(f, "Unable to spawn thread {thread}: {source}")
+            }
+            Self::Gui(reason) => write!(f, "Unable to start the GUI: {reason}"),
+            Self::NodesPanicked(panics) => {
+                write!(f, "{} thread(s) panicked:", panics.len())?;
+                for panic in panics {
+                    write!(f, "\n  - {}: {}", panic.node, panic.payload)?;
+                }
+                Ok(())
+            }
+            Self::JoinTimeout(threads) => {
+                write!(f, "{} thread(s) did not exit:", threads.len())?;
+                for thread in threads {
+                    write!(f, "\n  - {thread}")?;
+                }
+                Ok(())
+            }
+            Self::UnknownNode { node, kind } => write!(f, "There is no {kind} with id {node}"),
+            Self::NodeStopped { node } => write!(f, "Node {node} is no longer running"),
+            Self::TooManyThreads { needed, max } => write!(
+                f,
+                "The network needs {needed} threads, more than the maximum of {max}"
+            ),
+            Self::ReadAssignment { path, source } => write!(
+                f,
+                "Unable to read assignment file {}: {source}",
+                path.display()
+            ),
+            Self::ParseAssignment { path, source } => write!(
+                f,
+                "Unable to parse assignment file {}: {source}",
+                path.display()
+            ),
+            Self::AssignmentMismatch { missing, extra } => write!(
+                f,
+                "The assignment doesn't match the config, drones missing from it: {missing:?}, drones not in the config: {extra:?}"
+            ),
+            Self::RepeatWithoutDuration => {
+                f.write_str("Repeated runs need a duration, each run must end by itself")
+            }
+            Self::InvalidImplementationMapId { path, key } => write!(
+                f,
+                "Implementation map {} has key {key}, expected a node id",
+                path.display()
+            ),
+            Self::ImplementationMapUnknownDrones(ids) => write!(
+                f,
+                "The implementation map lists drones not in the config: {ids:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NetworkInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. }
+            | Self::Write { source, .. }
+            | Self::ReadAssignment { source, .. }
+            | Self::Metrics { source, .. }
+            | Self::Spawn { source, .. } => Some(source),
+            Self::Parse { source: err, .. } | Self::ParseAssignment { source: err, .. } => {
+                Some(err)
+            }
+            Self::ExpandEnv { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Vec<ValidationError>> for NetworkInitError {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        Self::InvalidConfig(errors)
+    }
+}
@@ -0,0 +1,187 @@
+//! Crashing drones on a schedule given by the `[[fault]]` entries.
+//!
+//! The injector runs on its own thread from the moment the nodes start. At
+//! each crash it sends `Crash` to the drone and has its drone neighbors
+//! remove it, as the simulation controller does. At each restore the drone
+//! is built anew from its config entry, like a respawned drone, and its
+//! drone neighbors add it back. Clients and servers take other commands,
+//! they keep their sender to a crashed drone.
+//!
+//! A crashed drone drains its channel until every sender is gone, and the
+//! restored drone takes its packets from the same channel: until the old
+//! one is done, some packets still end in its crash handling.
+
+use colored::Colorize;
+use crossbeam_channel::Sender;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use wg_2024::{controller::DroneCommand, network::NodeId, packet::Packet};
+
+use crate::{
+    config::{Fault, NodeKind},
+    error::NodePanic,
+    shutdown::Shutdown,
+    supervisor::DroneSupervisor,
+    topology::{LiveTopology, Topology},
+};
+
+/// Longest sleep between two checks of the shutdown
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Crash,
+    Restore,
+}
+
+/// Schedule of a network's faults, with what's needed to carry them out
+pub(crate) struct FaultInjector {
+    // Sorted by time, crashes first on a tie
+    timeline: Vec<(Duration, Action, NodeId)>,
+    drone_send: HashMap<NodeId, (Sender<DroneCommand>, Sender<Packet>)>,
+    topology: Topology,
+    live_topology: LiveTopology,
+    rebuilder: Option<DroneSupervisor>,
+}
+
+impl FaultInjector {
+    /// `rebuilder` can only be left out when no fault restores its drone
+    pub(crate) fn new(
+        faults: &[Fault],
+        drone_send: &HashMap<NodeId, (Sender<DroneCommand>, Sender<Packet>)>,
+        topology: &Topology,
+        live_topology: &LiveTopology,
+        rebuilder: Option<DroneSupervisor>,
+    ) -> Self {
+        let mut timeline = Vec::new();
+        for fault in faults {
+            timeline.push((fault.crash_at, Action::Crash, fault.drone));
+            if let Some(restore_at) = fault.restore_at {
+                timeline.push((restore_at, Action::Restore, fault.drone));
+            }
+        }
+        timeline.sort_by_key(|(at, action, _)| (*at, *action == Action::Restore));
+
+        Self {
+            timeline,
+            drone_send: drone_send.clone(),
+            topology: topology.clone(),
+            live_topology: live_topology.clone(),
+            rebuilder,
+        }
+    }
+
+    /// Go through the timeline, stopping early once `shutdown` is triggered,
+    /// then wait for the restored drones. Returns their panics.
+    pub(crate) fn run(self, shutdown: &Shutdown) -> Vec<NodePanic> {
+        let started = Instant::now();
+        let mut restored = Vec::new();
+        for (at, action, drone) in &self.timeline {
+            if !wait_until(started + *at, shutdown) {
+                break;
+            }
+            match action {
+                Action::Crash => self.crash(*drone),
+                Action::Restore => {
+                    if let Some(handle) = self.restore(*drone) {
+                        restored.push((*drone, handle));
+                    }
+                }
+            }
+        }
+        // The retained channels would keep the restored drones running
+        drop(self);
+
+        restored
+            .into_iter()
+            .filter_map(|(id, handle)| {
+                let payload = handle.join().err()?;
+                Some(NodePanic::new(format!("drone {id}"), payload.as_ref()))
+            })
+            .collect()
+    }
+
+    fn crash(&self, drone: NodeId) {
+        info!(
+            "[ {} ] Injecting fault: crashing [ Drone {drone} ]",
+            "Network Initializer".green()
+        );
+        if let Some((command_send, _)) = self.drone_send.get(&drone) {
+            self.send(drone, command_send, DroneCommand::Crash);
+        }
+        for (neighbor, command_send) in self.drone_neighbors(drone) {
+            self.send(neighbor, command_send, DroneCommand::RemoveSender(drone));
+        }
+    }
+
+    fn restore(&self, drone: NodeId) -> Option<JoinHandle<()>> {
+        info!(
+            "[ {} ] Injecting fault: restoring [ Drone {drone} ]",
+            "Network Initializer".green()
+        );
+        let (Some(rebuilder), Some((_, packet_send))) =
+            (&self.rebuilder, self.drone_send.get(&drone))
+        else {
+            return None;
+        };
+        let handle = match rebuilder.respawn_drone(drone) {
+            Ok(handle) => handle,
+            Err(err) => {
+                warn!(
+                    "[ {} ] Unable to restore [ Drone {drone} ]: {err}",
+                    "Network Initializer".yellow()
+                );
+                return None;
+            }
+        };
+        self.live_topology
+            .add_drone(drone, self.topology.neighbors(drone));
+        for (neighbor, command_send) in self.drone_neighbors(drone) {
+            let command = DroneCommand::AddSender(drone, packet_send.clone());
+            self.send(neighbor, command_send, command);
+        }
+        Some(handle)
+    }
+
+    /// Send `command` to `drone` and record it in the live topology
+    fn send(&self, drone: NodeId, command_send: &Sender<DroneCommand>, command: DroneCommand) {
+        self.live_topology.record(drone, &command);
+        // A drone that already exited can't receive, that's fine
+        let _ = command_send.send(command);
+    }
+
+    /// Drones next to `drone`, with their command sender
+    fn drone_neighbors(
+        &self,
+        drone: NodeId,
+    ) -> impl Iterator<Item = (NodeId, &Sender<DroneCommand>)> {
+        self.topology
+            .neighbors(drone)
+            .iter()
+            .filter(|neighbor| self.topology.kind(**neighbor) == Some(NodeKind::Drone))
+            .filter_map(|neighbor| {
+                let (command_send, _) = self.drone_send.get(neighbor)?;
+                Some((*neighbor, command_send))
+            })
+    }
+}
+
+/// Sleep until `deadline`, or return `false` as soon as `shutdown` is
+/// triggered
+fn wait_until(deadline: Instant, shutdown: &Shutdown) -> bool {
+    loop {
+        if shutdown.is_triggered() {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep((deadline - now).min(POLL_INTERVAL));
+    }
+}
@@ -0,0 +1,46 @@
+pub mod assignment;
+pub mod commands;
+pub mod config;
+pub mod controller;
+pub mod dot;
+pub mod error;
+pub mod faults;
+pub mod link;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod network_initializer;
+pub mod pause;
+pub mod plan;
+pub mod probe;
+pub mod ready;
+pub mod registry;
+pub mod reload;
+pub mod repl;
+pub mod sentinel;
+pub mod shutdown;
+pub mod snapshot;
+pub mod stats;
+pub mod supervisor;
+pub mod test_support;
+pub mod timing;
+pub mod topology;
+pub mod validation;
+
+pub use commands::NodeCommands;
+pub use config::{ConfigBuilder, NetworkConfig};
+pub use controller::{Controller, ControllerChannels, ControllerFactory};
+pub use network_initializer::{
+    apply_server_kinds, apply_server_mix, apply_server_remainder, build_channels, classify_clients,
+    classify_servers, positional_server_split, run, run_many, run_repeated, run_with_config,
+    run_with_fragments, run_with_registry, Channels, DroneAssignment, Network, RunOptions,
+    RunningNetwork,
+};
+pub use probe::{health_check, verify_reachability, Health};
+pub use ready::ReadyCallback;
+pub use registry::{
+    drone_factory, ClientRegistry, DroneRegistry, Node, Registries, ServerRegistry,
+};
+pub use snapshot::{snapshot, Snapshot};
+pub use stats::{EventObserver, EventTaps};
+pub use topology::{build_topology, dump_topology, LiveTopology, Topology};
+pub use validation::IdPolicy;
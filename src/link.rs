@@ -0,0 +1,145 @@
+//! Propagation delay on the edges of the network.
+//!
+//! Packets normally go straight into the receiving node's channel. A delayed
+//! edge gets a link thread in each direction instead: the sending node is
+//! handed the sender of an intermediate channel in place of its neighbor's,
+//! and the link forwards every packet to the neighbor after the delay.
+//! Packets on the same link queue behind each other, so a link also carries
+//! at most one packet per delay.
+//!
+//! Only the wiring done at startup goes through the links. Drones rebuilt
+//! after a panic or a fault, and drones added by the config reloader, get
+//! the same [`LinkSenders`] and so send through the links of their edges
+//! that had one at startup. Senders the simulation controller hands out
+//! with `AddSender`, and those sent to the neighbors of an added drone, go
+//! directly.
+
+use crossbeam_channel::{Receiver, Sender};
+use std::{borrow::Cow, collections::HashMap, thread, time::Duration};
+
+use wg_2024::{network::NodeId, packet::Packet};
+
+use crate::{
+    config::{self, NetworkConfig},
+    error::NetworkInitError,
+    network_initializer::{packet_channel, spawn_named},
+};
+
+/// One direction of a delayed edge
+struct Link {
+    from: NodeId,
+    to: NodeId,
+    delay: Duration,
+    packet_recv: Receiver<Packet>,
+    packet_send: Sender<Packet>,
+}
+
+/// Sender of the link of every delayed edge, keyed by `(from, to)`. Kept by
+/// whatever builds drones after startup, every clone keeps the links open.
+#[derive(Clone, Default)]
+pub struct LinkSenders(HashMap<(NodeId, NodeId), Sender<Packet>>);
+
+impl LinkSenders {
+    /// Packet senders `node` must use: the ones of `packet_send`, with those
+    /// of its delayed edges replaced by their link
+    pub(crate) fn senders_of<'a>(
+        &self,
+        node: NodeId,
+        packet_send: &'a HashMap<NodeId, Sender<Packet>>,
+    ) -> Cow<'a, HashMap<NodeId, Sender<Packet>>> {
+        let mut senders = Cow::Borrowed(packet_send);
+        for ((from, to), link_send) in &self.0 {
+            if *from == node {
+                senders.to_mut().insert(*to, link_send.clone());
+            }
+        }
+        senders
+    }
+}
+
+/// Every link of a network, with the senders the nodes send through
+#[derive(Default)]
+pub(crate) struct Links {
+    senders: LinkSenders,
+    links: Vec<Link>,
+}
+
+impl Links {
+    /// A link in both directions of every edge with a delay: its own from
+    /// `network_config`, or else `default_delay`. `packet_send` holds the
+    /// real packet sender of every node, intermediate channels get
+    /// `capacity` like the others.
+    pub(crate) fn new(
+        network_config: &NetworkConfig,
+        default_delay: Option<Duration>,
+        packet_send: &HashMap<NodeId, Sender<Packet>>,
+        capacity: Option<usize>,
+    ) -> Self {
+        let mut links = Self::default();
+        for (a, b) in config::edges(&network_config.config) {
+            let delay = network_config
+                .link_delays
+                .get(&(a, b))
+                .copied()
+                .or(default_delay)
+                .filter(|delay| !delay.is_zero());
+            let Some(delay) = delay else {
+                continue;
+            };
+
+            for (from, to) in [(a, b), (b, a)] {
+                // Edges to undefined nodes are rejected by the validation
+                let Some(real_send) = packet_send.get(&to) else {
+                    continue;
+                };
+                let (link_send, link_recv) = packet_channel(capacity);
+                links.senders.0.insert((from, to), link_send);
+                links.links.push(Link {
+                    from,
+                    to,
+                    delay,
+                    packet_recv: link_recv,
+                    packet_send: real_send.clone(),
+                });
+            }
+        }
+        links
+    }
+
+    /// Packet senders `node` must use, see [`LinkSenders::senders_of`]
+    pub(crate) fn senders_of<'a>(
+        &self,
+        node: NodeId,
+        packet_send: &'a HashMap<NodeId, Sender<Packet>>,
+    ) -> Cow<'a, HashMap<NodeId, Sender<Packet>>> {
+        self.senders.senders_of(node, packet_send)
+    }
+
+    /// Senders of every link, for the drones built after startup
+    pub(crate) fn senders(&self) -> LinkSenders {
+        self.senders.clone()
+    }
+
+    /// Number of link threads
+    pub(crate) fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Run every link on its own thread. A link ends once the node sending
+    /// through it is gone, along with every [`LinkSenders`] clone.
+    pub(crate) fn spawn(self) -> Result<(), NetworkInitError> {
+        // The nodes hold their own clones, the links must not keep theirs open
+        drop(self.senders);
+        for link in self.links {
+            spawn_named(format!("link-{}-{}", link.from, link.to), move || {
+                for packet in &link.packet_recv {
+                    thread::sleep(link.delay);
+                    if link.packet_send.send(packet).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -1,19 +1,439 @@
+use colored::Colorize;
 use slog::{slog_o, Drain};
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
 
-mod network_initializer;
+use network_initializer::{
+    config::{ConfigFormat, ServerKind},
+    error::NetworkInitError,
+    network_initializer::{self as initializer, load_configs, DEFAULT_CONFIG_PATH, STDIN_PATH},
+    validation, DroneAssignment, IdPolicy, RunOptions,
+};
 
-fn main() {
-    let decorator = slog_term::TermDecorator::new().build();
+/// Log level given on the command line, or else taken from `RUST_LOG`,
+/// `info` when unset or not a level
+fn log_level(verbosity: Option<log::Level>) -> log::Level {
+    verbosity.unwrap_or_else(|| {
+        std::env::var("RUST_LOG")
+            .ok()
+            .and_then(|level| level.trim().parse().ok())
+            .unwrap_or(log::Level::Info)
+    })
+}
+
+/// Whether the output can hold color escape codes: not when `NO_COLOR` is
+/// set or stderr, where logs go, isn't a terminal
+fn use_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && std::io::stderr().is_terminal()
+}
+
+/// What the command line asks for, given by its first argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Run,
+    /// `lint`: check the config instead of running it
+    Lint,
+    /// `convert in out`: write the config `in` to `out` in the binary format
+    Convert,
+}
+
+/// Command line arguments, see [`parse_args`]
+struct Args {
+    command: Command,
+    /// `--repeat`: run the simulation this many times in a row
+    repeat: Option<usize>,
+    /// Every other flag, and in `config_paths` the config paths included,
+    /// never empty
+    options: RunOptions,
+    /// Log level chosen with `--quiet`, `-v` or `-vv`
+    verbosity: Option<log::Level>,
+}
+
+/// Read the config paths and the options from the command line arguments.
+/// The positional arguments are config fragments merged into a single
+/// config, default to the one shipped with the crate, `-` for the standard
+/// input. A leading `lint` checks the config instead of running it, a
+/// leading `convert` takes exactly an input and an output path.
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.peekable();
+    let command = match args.next_if(|arg| arg == "lint" || arg == "convert") {
+        Some(arg) if arg == "lint" => Command::Lint,
+        Some(_) => Command::Convert,
+        None => Command::Run,
+    };
+    let mut options = RunOptions::default();
+    let mut verbosity = None;
+    let mut repeat = None;
+    // Applied after `--seed`, whatever the order
+    let (mut pdr_seed, mut server_mix_seed) = (None, None);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict-drones" => options.drone_assignment = DroneAssignment::Strict,
+            "--spread-implementations" => options.drone_assignment = DroneAssignment::Spread,
+            "--quiet" | "-q" => verbosity = Some(log::Level::Error),
+            "-v" => verbosity = Some(log::Level::Debug),
+            "-vv" => verbosity = Some(log::Level::Trace),
+            "--headless" => options.headless = true,
+            "--repl" => options.repl = true,
+            "--pausable" => options.pausable = true,
+            "--expand-env" => options.expand_env = true,
+            "--dry-run" => options.dry_run = true,
+            "--respawn-drones" => options.respawn_drones = true,
+            "--throughput" => options.throughput_meter = true,
+            "--no-clients" => options.no_clients = true,
+            "--no-servers" => options.no_servers = true,
+            "--no-topology-event" => options.no_topology_event = true,
+            "--watch-config" => options.watch_config = true,
+            "--strict-connectivity" => options.strict_connectivity = true,
+            "--dot" => options.dot_output = Some(value(&arg, args.next())?.into()),
+            "--print-topology" => options.print_topology = true,
+            "--metrics-port" => {
+                let port = value(&arg, args.next())?;
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("Invalid port {port}, expected 0 to 65535"))?;
+                options.metrics_port = Some(port);
+            }
+            "--summary-json" => {
+                options.summary_json = Some(value(&arg, args.next())?.into());
+            }
+            "--snapshot-out" => {
+                options.snapshot_out = Some(value(&arg, args.next())?.into());
+            }
+            "--stats-csv" => {
+                options.stats_csv = Some(value(&arg, args.next())?.into());
+            }
+            "--assignment-out" => {
+                options.assignment_out = Some(value(&arg, args.next())?.into());
+            }
+            "--repeat" => {
+                let runs = value(&arg, args.next())?;
+                repeat = Some(
+                    runs.parse()
+                        .ok()
+                        .filter(|runs| *runs > 0)
+                        .ok_or_else(|| format!("Invalid run count {runs}"))?,
+                );
+            }
+            "--impl-map" => options.impl_map = Some(value(&arg, args.next())?.into()),
+            "--assignment-in" => {
+                options.assignment_in = Some(value(&arg, args.next())?.into());
+            }
+            "--drone-impl" => options.drone_implementation = Some(value(&arg, args.next())?),
+            "--seed" => {
+                let seed = value(&arg, args.next())?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid seed {seed}, expected an unsigned integer"))?;
+                options.seed(seed);
+            }
+            "--pdr" => {
+                let pdr = value(&arg, args.next())?;
+                let pdr = pdr
+                    .parse()
+                    .map_err(|_| format!("Invalid pdr {pdr}, expected a number"))?;
+                options.pdr_override = Some(pdr);
+            }
+            "--max-threads" => {
+                let max = value(&arg, args.next())?;
+                let max = max.parse().map_err(|_| {
+                    format!("Invalid thread count {max}, expected an unsigned integer")
+                })?;
+                options.max_threads = Some(max);
+            }
+            "--pdr-range" => {
+                let range = value(&arg, args.next())?;
+                let (min, max) = range
+                    .split_once(':')
+                    .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+                    .ok_or_else(|| format!("Invalid pdr range {range}, expected min:max"))?;
+                options.pdr_range = Some((min, max));
+            }
+            "--server-mix" => {
+                let mix = value(&arg, args.next())?;
+                let weights: Option<Vec<f64>> =
+                    mix.split(':').map(|weight| weight.parse().ok()).collect();
+                let Some(&[communication, text, media]) = weights.as_deref() else {
+                    return Err(format!(
+                        "Invalid server mix {mix}, expected comm:text:media"
+                    ));
+                };
+                options.server_mix = Some((communication, text, media));
+            }
+            "--server-kinds" => {
+                let kinds = value(&arg, args.next())?;
+                let mut allowed = Vec::new();
+                for name in kinds.split(',') {
+                    let kind = match name {
+                        "comm" | "communication" => ServerKind::Communication,
+                        "text" => ServerKind::Text,
+                        "media" => ServerKind::Media,
+                        _ => {
+                            return Err(format!(
+                                "Invalid server kind {name}, expected comm, text or media"
+                            ))
+                        }
+                    };
+                    if !allowed.contains(&kind) {
+                        allowed.push(kind);
+                    }
+                }
+                options.server_kinds = Some(allowed);
+            }
+            "--server-remainder" => {
+                options.server_remainder = match value(&arg, args.next())?.as_str() {
+                    "comm" | "communication" => Some(ServerKind::Communication),
+                    "text" => Some(ServerKind::Text),
+                    "media" => Some(ServerKind::Media),
+                    kind => {
+                        return Err(format!(
+                            "Invalid server remainder {kind}, expected comm, text or media"
+                        ))
+                    }
+                };
+            }
+            "--server-mix-seed" => {
+                let seed = value(&arg, args.next())?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid seed {seed}, expected an unsigned integer"))?;
+                server_mix_seed = Some(seed);
+            }
+            "--pdr-seed" => {
+                let seed = value(&arg, args.next())?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid seed {seed}, expected an unsigned integer"))?;
+                pdr_seed = Some(seed);
+            }
+            "--channel-capacity" => {
+                let capacity = value(&arg, args.next())?;
+                let capacity = capacity
+                    .parse()
+                    .ok()
+                    .filter(|capacity| *capacity > 0)
+                    .ok_or_else(|| {
+                        format!("Invalid channel capacity {capacity}, expected a positive integer")
+                    })?;
+                options.channel_capacity = Some(capacity);
+            }
+            "--id-policy" => {
+                options.id_policy = match value(&arg, args.next())?.as_str() {
+                    "permissive" => IdPolicy::Permissive,
+                    "no-reserved" => IdPolicy::NoReserved,
+                    "contiguous" => IdPolicy::Contiguous,
+                    policy => {
+                        return Err(format!(
+                            "Invalid id policy {policy}, expected permissive, no-reserved or contiguous"
+                        ))
+                    }
+                };
+            }
+            "--format" => {
+                options.config_format = match value(&arg, args.next())?.as_str() {
+                    "toml" => Some(ConfigFormat::Toml),
+                    "json" => Some(ConfigFormat::Json),
+                    "yaml" => Some(ConfigFormat::Yaml),
+                    "bin" => Some(ConfigFormat::Binary),
+                    format => {
+                        return Err(format!(
+                            "Invalid format {format}, expected toml, json, yaml or bin"
+                        ))
+                    }
+                };
+            }
+            "--link-delay" => {
+                let delay = value(&arg, args.next())?;
+                let delay = parse_duration(&delay).ok_or_else(|| {
+                    format!("Invalid link delay {delay}, expected e.g. 20ms or 1s")
+                })?;
+                options.link_delay = Some(delay);
+            }
+            "--duration" => {
+                let duration = value(&arg, args.next())?;
+                let duration = parse_duration(&duration).ok_or_else(|| {
+                    format!("Invalid duration {duration}, expected e.g. 60 or 500ms")
+                })?;
+                options.duration = Some(duration);
+            }
+            "--drain" => {
+                let quiet = value(&arg, args.next())?;
+                let quiet = parse_duration(&quiet).ok_or_else(|| {
+                    format!("Invalid drain period {quiet}, expected e.g. 1s or 500ms")
+                })?;
+                options.drain = Some(quiet);
+            }
+            "--drain-timeout" => {
+                let timeout = value(&arg, args.next())?;
+                let timeout = parse_duration(&timeout).ok_or_else(|| {
+                    format!("Invalid drain timeout {timeout}, expected e.g. 10s or 500ms")
+                })?;
+                options.drain_timeout = Some(timeout);
+            }
+            "--join-timeout" => {
+                let timeout = value(&arg, args.next())?;
+                let timeout = parse_duration(&timeout).ok_or_else(|| {
+                    format!("Invalid join timeout {timeout}, expected e.g. 30s or 500ms")
+                })?;
+                options.join_timeout = Some(timeout);
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+            _ => {
+                options.config_path(arg);
+            }
+        }
+    }
+    options.pdr_seed = pdr_seed.or(options.pdr_seed);
+    options.server_mix_seed = server_mix_seed.or(options.server_mix_seed);
+
+    if command == Command::Convert && options.config_paths.len() != 2 {
+        return Err("convert takes an input and an output path".to_string());
+    }
+    if options.config_paths.is_empty() {
+        options.config_path(DEFAULT_CONFIG_PATH);
+    }
+    let stdin = Path::new(STDIN_PATH);
+    if options.repl && options.config_paths.iter().any(|path| path == stdin) {
+        return Err("--repl reads the standard input, the config can't come from it".to_string());
+    }
+    if options.drain_timeout.is_some() && options.drain.is_none() {
+        return Err("--drain-timeout needs --drain".to_string());
+    }
+    if options.server_kinds.is_some() && options.server_mix.is_some() {
+        return Err("--server-kinds and --server-mix can't be combined".to_string());
+    }
+    if repeat.is_some() && options.duration.is_none() {
+        return Err("--repeat needs --duration, each run must end by itself".to_string());
+    }
+    Ok(Args {
+        command,
+        repeat,
+        options,
+        verbosity,
+    })
+}
+
+/// Duration written as a number of milliseconds (`500ms`), seconds (`30s`,
+/// or no unit) or minutes (`2m`)
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(number)),
+        "s" => Some(Duration::from_secs(number)),
+        "m" => Some(Duration::from_secs(number.checked_mul(60)?)),
+        _ => None,
+    }
+}
+
+/// Print every problem of the config at `config_paths`, see
+/// [`validation::lint`], and fail if any was found
+fn lint(config_paths: &[PathBuf], options: &RunOptions) -> ExitCode {
+    let problems = match load_configs(config_paths, options.config_format, options.expand_env) {
+        Ok(network_config) => validation::lint(&network_config, options.id_policy),
+        // Ids shared by two fragments
+        Err(NetworkInitError::InvalidConfig(problems)) => problems,
+        Err(err) => {
+            eprintln!("[ {} ] {err}", "Network Initializer".red());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if problems.is_empty() {
+        println!("No problem found");
+        ExitCode::SUCCESS
+    } else {
+        println!("{} problem(s) found", problems.len());
+        ExitCode::FAILURE
+    }
+}
+
+/// Write the config `input` to `output` in the binary format
+fn convert(input: &Path, output: &Path, options: &RunOptions) -> ExitCode {
+    match initializer::convert(input, options.config_format, output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("[ {} ] {err}", "Network Initializer".red());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The value following an option that requires one
+fn value(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("Missing value for {option}"))
+}
+
+fn main() -> ExitCode {
+    let color = use_color();
+    if !color {
+        colored::control::set_override(false);
+    }
+
+    let Args {
+        command,
+        repeat,
+        options,
+        verbosity,
+    } = match parse_args(std::env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("[ {} ] {err}", "Network Initializer".red());
+            return ExitCode::FAILURE;
+        }
+    };
+    match command {
+        Command::Run => {}
+        Command::Lint => return lint(&options.config_paths, &options),
+        Command::Convert => {
+            return convert(&options.config_paths[0], &options.config_paths[1], &options)
+        }
+    }
+    let quiet = verbosity == Some(log::Level::Error);
+
+    let decorator = slog_term::TermDecorator::new().stderr();
+    let decorator = if color {
+        decorator.build()
+    } else {
+        decorator.force_plain().build()
+    };
     let drain = slog_term::FullFormat::new(decorator).build().fuse();
     let drain = slog_async::Async::new(drain).build().fuse();
     let logger = slog::Logger::root(drain, slog_o!("version" => env!("CARGO_PKG_VERSION")));
 
+    // The guard must outlive run(), otherwise the global logger is dropped
     let _scope_guard = slog_scope::set_global_logger(logger);
-    slog_stdlog::init_with_level(log::Level::Info).unwrap();
+    slog_stdlog::init_with_level(log_level(verbosity)).unwrap();
 
-    println!("Start!");
+    if !quiet {
+        println!("Start!");
+    }
 
-    network_initializer::run();
+    let result = match repeat {
+        Some(runs) => {
+            network_initializer::run_repeated(options, &Default::default(), runs).map(drop)
+        }
+        None => network_initializer::run(options),
+    };
+    if let Err(err) = result {
+        eprintln!("[ {} ] {err}", "Network Initializer".red());
+        return ExitCode::FAILURE;
+    }
 
-    println!("Finish!");
+    if !quiet {
+        println!("Finish!");
+    }
+    ExitCode::SUCCESS
 }
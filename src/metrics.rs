@@ -0,0 +1,161 @@
+//! Serving the packet stats to Prometheus while the network runs.
+//!
+//! A thread answers every HTTP request on `127.0.0.1:<port>` with the
+//! counters the event tee keeps for each drone, sent and dropped, plus the
+//! totals, in the Prometheus text format. It only reads the stats table, the
+//! simulation is the same with or without it. The server is the bare minimum
+//! a scraper needs: one request per connection, any path but `/` or
+//! `/metrics` is a 404.
+
+use colored::Colorize;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use wg_2024::network::NodeId;
+
+use crate::{
+    error::NetworkInitError,
+    network_initializer::spawn_named,
+    shutdown::Shutdown,
+    stats::{self, PacketStats, StatsTable},
+};
+
+/// How often the server checks whether the network stopped between requests
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Longest wait for a scraper to send its request or read the answer
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `# HELP` and `# TYPE` lines of the gauge `name`
+fn describe(text: &mut String, name: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {name} {help}");
+    let _ = writeln!(text, "# TYPE {name} gauge");
+}
+
+/// The stats of every drone, sorted by id, and their totals, in the
+/// Prometheus text format
+pub fn prometheus_text(stats: &HashMap<NodeId, PacketStats>) -> String {
+    let mut ids: Vec<&NodeId> = stats.keys().collect();
+    ids.sort_unstable();
+    let totals = stats::totals(stats);
+
+    let mut text = String::new();
+    // `write!` on a String can't fail
+    describe(&mut text, "drone_packets_sent", "Packets sent by the drone");
+    for id in &ids {
+        let _ = writeln!(
+            text,
+            "drone_packets_sent{{drone=\"{id}\"}} {}",
+            stats[*id].sent
+        );
+    }
+    describe(
+        &mut text,
+        "drone_packets_dropped",
+        "Packets dropped by the drone",
+    );
+    for id in &ids {
+        let _ = writeln!(
+            text,
+            "drone_packets_dropped{{drone=\"{id}\"}} {}",
+            stats[*id].dropped
+        );
+    }
+    describe(
+        &mut text,
+        "packets_sent_total",
+        "Packets sent by every drone",
+    );
+    let _ = writeln!(text, "packets_sent_total {}", totals.sent);
+    describe(
+        &mut text,
+        "packets_dropped_total",
+        "Packets dropped by every drone",
+    );
+    let _ = writeln!(text, "packets_dropped_total {}", totals.dropped);
+    text
+}
+
+/// Serve the stats of `stats` on `127.0.0.1:port` from the thread `metrics`
+/// until `shutdown` is triggered. Fails when the port can't be bound.
+pub(crate) fn serve(
+    port: u16,
+    stats: &StatsTable,
+    shutdown: &Shutdown,
+) -> Result<(), NetworkInitError> {
+    let bind = || {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        // Polled, so the thread notices the shutdown
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    };
+    let listener = bind().map_err(|source| NetworkInitError::Metrics { port, source })?;
+    info!(
+        "[ {} ] Serving metrics on http://127.0.0.1:{port}/metrics",
+        "Network Initializer".green()
+    );
+
+    let (stats, shutdown) = (stats.clone(), shutdown.clone());
+    spawn_named("metrics".to_string(), move || {
+        while !shutdown.is_triggered() {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(err) = answer(stream, &stats) {
+                        warn!(
+                            "[ {} ] Unable to answer a metrics request: {err}",
+                            "Network Initializer".yellow()
+                        );
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(err) => {
+                    warn!(
+                        "[ {} ] Unable to accept a metrics connection: {err}",
+                        "Network Initializer".yellow()
+                    );
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    })?;
+    Ok(())
+}
+
+/// Read the request of `stream` and answer it
+fn answer(mut stream: TcpStream, stats: &StatsTable) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    // Only the request line matters, the headers are read and ignored
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < 16 * 1024 {
+        let read = stream.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, body) = match path {
+        "/" | "/metrics" => ("200 OK", prometheus_text(&stats::snapshot(stats))),
+        _ => ("404 Not Found", "not found, try /metrics\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
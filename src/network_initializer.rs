@@ -1,115 +1,1204 @@
 use colored::Colorize;
-use crossbeam_channel::{unbounded, Receiver, Sender};
-use log::info;
-use std::{collections::HashMap, fs, thread};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use log::{error, info, trace, warn};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{self, Write},
+    fs,
+    io::{self, Read},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{Arc, Barrier},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
 use wg_2024::{
-    config::{Config, Drone as ConfigDrone},
+    config::Config,
     controller::{DroneCommand, DroneEvent},
     drone::Drone,
     network::NodeId,
     packet::Packet,
 };
 
-use chat_client::ChatClient;
-use communication_server::servers::{
-    communication_server::CommunicationServer, content_server::ContentServer,
-};
-
 use gui::app::SimCtrlGUI;
 
-use media_client::media_client::MediaClient;
 use messages::{
     client_commands::{ChatClientCommand, ChatClientEvent, MediaClientCommand, MediaClientEvent},
     gui_commands::{GUICommands, GUIEvents},
-    high_level_messages::ServerType,
     server_commands::{
         CommunicationServerCommand, CommunicationServerEvent, ContentServerCommand,
         ContentServerEvent,
     },
 };
-use simulation_controller::SimulationController;
 
-type DroneFactoryFn = dyn Fn(
-    &ConfigDrone,
-    &Sender<DroneEvent>,
-    &HashMap<NodeId, Receiver<DroneCommand>>,
-    &HashMap<NodeId, Sender<Packet>>,
-    &HashMap<NodeId, Receiver<Packet>>,
-) -> Box<dyn Drone>;
+use crate::{
+    assignment::{Assignment, ImplementationMap},
+    commands::NodeCommands,
+    config::{self, ClientKind, ConfigFormat, InitCommand, NetworkConfig, ParseError, ServerKind},
+    controller::{Controller, ControllerChannels, ControllerFactory},
+    dot,
+    error::{panic_message, NetworkInitError, NodePanic},
+    faults::FaultInjector,
+    link::Links,
+    pause, plan,
+    probe::Probes,
+    ready::{self, ReadyCallback},
+    registry::{
+        self, DroneRegistry, Node, Registries, DEFAULT_CHAT_CLIENT, DEFAULT_COMMUNICATION_SERVER,
+        DEFAULT_CONTENT_SERVER, DEFAULT_MEDIA_CLIENT,
+    },
+    reload::ConfigReloader,
+    repl,
+    sentinel::EventSentinel,
+    shutdown::{Shutdown, ShutdownSlot, DEFAULT_DRAIN_TIMEOUT},
+    snapshot,
+    stats::{self, EventObserver, EventTaps, PacketCounter, PacketStats, StatsTable, Subscribers},
+    supervisor::DroneSupervisor,
+    timing::StartupTimings,
+    topology::{build_topology, LiveTopology, Topology},
+    validation::{self, IdPolicy},
+};
+
+/// How drones without an explicit `implementation` get one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DroneAssignment {
+    /// Cycle through the implementations, so any number of drones is fine
+    #[default]
+    RoundRobin,
+    /// One implementation per drone, error when there are more drones than implementations
+    Strict,
+    /// Pseudo-random implementations, the same seed always gives the same assignment
+    Seeded(u64),
+    /// Always the least used implementation so far, so as many as possible run
+    Spread,
+}
+
+/// How often threads are checked while waiting for them with a timeout
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long threads get to exit after the stop broadcast sent on timeout
+const JOIN_GRACE: Duration = Duration::from_secs(5);
+
+/// Implementation name chosen for every drone
+pub type FactoryAssignment = HashMap<NodeId, String>;
+
+/// Options that change how the network is built
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Config files [`run`] reads, merged by [`load_configs`] when there
+    /// are several, [`DEFAULT_CONFIG_PATH`] when empty
+    pub config_paths: Vec<PathBuf>,
+    pub drone_assignment: DroneAssignment,
+    /// Implementation given to every drone, whatever the config and
+    /// `drone_assignment` say
+    pub drone_implementation: Option<String>,
+    /// Run without the GUI, the simulation controller takes the main thread
+    pub headless: bool,
+    /// Fail instead of warning when a node is unreachable or isolated
+    pub strict_connectivity: bool,
+    /// Write the topology as a Graphviz file there and exit without spawning
+    pub dot_output: Option<PathBuf>,
+    /// Packet drop rate given to every drone instead of the one in the config
+    pub pdr_override: Option<f32>,
+    /// Draw the packet drop rate of every drone uniformly from `[min, max]`
+    /// instead of taking the one in the config. `pdr_override` wins when
+    /// both are set.
+    pub pdr_range: Option<(f32, f32)>,
+    /// Seed of the `pdr_range` draws, the same seed gives the same rates.
+    /// Drones added by the config reloader always get unseeded draws.
+    pub pdr_seed: Option<u64>,
+    /// Check the config and print what would be built, without spawning
+    pub dry_run: bool,
+    /// Leave every client out of the network, only their edges to drones are
+    /// noticed, with a warning
+    pub no_clients: bool,
+    /// Leave every server out of the network, the same way
+    pub no_servers: bool,
+    /// Don't send the GUI the `Topology` event with the config entries, for
+    /// a GUI that doesn't use it. The `GUICommands` of the `messages` crate
+    /// have no request for the topology, such a GUI must learn it otherwise.
+    pub no_topology_event: bool,
+    /// Write a JSON description of the built network there before spawning
+    pub summary_json: Option<PathBuf>,
+    /// Print every node of the built network with its kind, implementation
+    /// and neighbors as a table before spawning
+    pub print_topology: bool,
+    /// Write a snapshot of the built network there before spawning, as TOML
+    /// or as JSON for paths ending in `.json`
+    pub snapshot_out: Option<PathBuf>,
+    /// Build a drone again and restart it when its thread panics
+    pub respawn_drones: bool,
+    /// Add and remove drones when the config file changes
+    pub watch_config: bool,
+    /// How long to wait for the threads to exit once the simulation is over,
+    /// forever when `None`
+    pub join_timeout: Option<Duration>,
+    /// Capacity of every packet channel, unbounded when `None`.
+    ///
+    /// A full channel blocks its senders, which applies backpressure but can
+    /// deadlock: two neighbors flooding each other both wait for the other
+    /// to receive. Each node's channel is created once, all its senders share
+    /// the capacity.
+    pub channel_capacity: Option<usize>,
+    /// Log how many packets the drones send per second while the network runs
+    pub throughput_meter: bool,
+    /// Serve the packet stats of every drone on this local port in the
+    /// Prometheus text format, see [`crate::metrics`]. Only with the
+    /// `metrics` feature, otherwise a warning says it's ignored.
+    pub metrics_port: Option<u16>,
+    /// Given every `DroneEvent` before the simulation controller gets it
+    pub event_observer: Option<EventObserver>,
+    /// Channels given a copy of the drone and server events
+    pub event_taps: EventTaps,
+    /// Called once every node runs, see [`crate::ready`]
+    pub on_ready: Option<ReadyCallback>,
+    /// Constraints the ids must follow on top of being unique
+    pub id_policy: IdPolicy,
+    /// Hand every node its packets through a relay thread, so the network
+    /// can be paused. Drones added by the config reloader get no relay.
+    pub pausable: bool,
+    /// Language of the config file, guessed from its extension when `None`
+    pub config_format: Option<ConfigFormat>,
+    /// Delay of every edge without one of its own in the config, see
+    /// [`crate::link`]
+    pub link_delay: Option<Duration>,
+    /// Write the implementation and pdr every drone got there, TOML or JSON
+    /// for paths ending in `.json`
+    pub assignment_out: Option<PathBuf>,
+    /// Give the drones listed in this map their implementation, over what
+    /// the config says. The other drones follow `drone_assignment`.
+    pub impl_map: Option<PathBuf>,
+    /// Load the implementation and pdr of every drone from a table written
+    /// with `assignment_out`, instead of `drone_assignment`, `pdr_range` and
+    /// `drone_implementation`
+    pub assignment_in: Option<PathBuf>,
+    /// Refuse to start a network needing more threads than this, see
+    /// [`Network::thread_count`]
+    pub max_threads: Option<usize>,
+    /// Stop the network on its own once it ran this long, as Ctrl-C would
+    pub duration: Option<Duration>,
+    /// Before stopping on `duration` or Ctrl-C, wait until the drones sent
+    /// no packet for this long, see [`Shutdown::drain`]
+    pub drain: Option<Duration>,
+    /// Longest wait for the drain, [`DEFAULT_DRAIN_TIMEOUT`] when `None`
+    pub drain_timeout: Option<Duration>,
+    /// Write the packet stats of every drone there as CSV once the network
+    /// is over, however it was stopped
+    pub stats_csv: Option<PathBuf>,
+    /// Weights of the communication, text and media kinds, drawn for every
+    /// server without an explicit `kind` instead of the positional thirds
+    pub server_mix: Option<(f64, f64, f64)>,
+    /// Seed of the `server_mix` draws, the same seed gives the same kinds
+    pub server_mix_seed: Option<u64>,
+    /// Only build servers of these kinds, the servers without an explicit
+    /// `kind` split evenly among them in this order. Every server gets a
+    /// kind this way, so `server_mix` is ignored.
+    pub server_kinds: Option<Vec<ServerKind>>,
+    /// Kind getting the servers left over when their count isn't a multiple
+    /// of three, [`DEFAULT_SERVER_REMAINDER`] when `None`. Only the servers
+    /// split by position are concerned, so it does nothing with
+    /// `server_kinds` or `server_mix`.
+    pub server_remainder: Option<ServerKind>,
+    /// Read drone commands from the standard input while the network runs,
+    /// see [`crate::repl`]
+    pub repl: bool,
+    /// Replace the `${VAR}` references of the config files with environment
+    /// variables before parsing them
+    pub expand_env: bool,
+}
+
+/// Setters for the options most programs change, the others are set as
+/// fields, `RunOptions { pausable: true, ..Default::default() }`
+impl RunOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a config file to read, after the ones already given
+    pub fn config_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.config_paths.push(path.into());
+        self
+    }
+
+    pub fn headless(&mut self, headless: bool) -> &mut Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Seed of every random draw: the drone implementations, the pdr range
+    /// and the server mix
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.drone_assignment = DroneAssignment::Seeded(seed);
+        self.pdr_seed = Some(seed);
+        self.server_mix_seed = Some(seed);
+        self
+    }
+
+    /// Seed of the pdr range and server mix draws, and of the drone
+    /// assignment when it's already seeded: the strategy isn't changed
+    fn reseed(&mut self, seed: u64) {
+        self.pdr_seed = Some(seed);
+        self.server_mix_seed = Some(seed);
+        if let DroneAssignment::Seeded(_) = self.drone_assignment {
+            self.drone_assignment = DroneAssignment::Seeded(seed);
+        }
+    }
+
+    pub fn duration(&mut self, duration: Duration) -> &mut Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn channel_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.channel_capacity = Some(capacity);
+        self
+    }
+
+    pub fn drone_implementation(&mut self, implementation: impl Into<String>) -> &mut Self {
+        self.drone_implementation = Some(implementation.into());
+        self
+    }
+}
+
+/// Choose the implementation of every drone as `options` say: the same one
+/// for all when `drone_implementation` is set, or else
+/// [`assign_implementations`] with `drone_assignment`
+pub fn choose_implementations(
+    network_config: &NetworkConfig,
+    options: &RunOptions,
+) -> Result<FactoryAssignment, NetworkInitError> {
+    let Some(implementation) = &options.drone_implementation else {
+        return assign_implementations(network_config, options.drone_assignment);
+    };
+    info!(
+        "[ {} ] Every drone uses {implementation}",
+        "Network Initializer".green()
+    );
+    Ok(network_config
+        .config
+        .drone
+        .iter()
+        .map(|drone| (drone.id, implementation.clone()))
+        .collect())
+}
+
+/// Fail when the implementation forced on every drone isn't in `registry`,
+/// before anything is built
+fn check_drone_implementation(
+    options: &RunOptions,
+    registry: &DroneRegistry,
+) -> Result<(), NetworkInitError> {
+    match &options.drone_implementation {
+        Some(implementation) if !registry.contains(implementation) => {
+            Err(NetworkInitError::UnknownForcedImplementation {
+                implementation: implementation.clone(),
+                available: registry.names().into_iter().map(String::from).collect(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Choose the implementation of every drone: the one requested in the
+/// config, or else one picked following `assignment`
+pub fn assign_implementations(
+    network_config: &NetworkConfig,
+    assignment: DroneAssignment,
+) -> Result<FactoryAssignment, NetworkInitError> {
+    let mut rng = match assignment {
+        DroneAssignment::Seeded(seed) => {
+            info!(
+                "[ {} ] Assigning drone implementations with seed {seed}",
+                "Network Initializer".green()
+            );
+            Some(StdRng::seed_from_u64(seed))
+        }
+        _ => None,
+    };
+
+    // Usage of every distinct implementation, explicit choices included
+    let mut usage: Vec<(&str, usize)> = distinct_implementations()
+        .into_iter()
+        .map(|name| {
+            let explicit = network_config
+                .drone_implementations
+                .values()
+                .filter(|implementation| implementation.as_str() == name)
+                .count();
+            (name, explicit)
+        })
+        .collect();
+
+    // Implementations disabled at compile time are left out
+    let order = registry::default_drone_order();
+
+    let mut implementations = FactoryAssignment::new();
+    for (n, drone) in network_config.config.drone.iter().enumerate() {
+        let implementation = match network_config.drone_implementations.get(&drone.id) {
+            Some(implementation) => implementation.clone(),
+            None if assignment == DroneAssignment::Spread => {
+                // The first of the least used ones, keeping the default order on ties
+                let least_used = usage.iter_mut().min_by_key(|(_, count)| *count).ok_or(
+                    NetworkInitError::NoFactoryForDrone {
+                        index: n,
+                        drone: drone.id,
+                    },
+                )?;
+                least_used.1 += 1;
+                least_used.0.to_string()
+            }
+            None => {
+                // Out of range whenever `order` is empty
+                let index = match assignment {
+                    DroneAssignment::RoundRobin => n.checked_rem(order.len()).unwrap_or(n),
+                    DroneAssignment::Strict | DroneAssignment::Spread => n,
+                    DroneAssignment::Seeded(_) => match rng.as_mut() {
+                        Some(rng) if !order.is_empty() => rng.random_range(0..order.len()),
+                        _ => n,
+                    },
+                };
+                order
+                    .get(index)
+                    .ok_or(NetworkInitError::NoFactoryForDrone {
+                        index: n,
+                        drone: drone.id,
+                    })?
+                    .to_string()
+            }
+        };
+
+        info!(
+            "[ {} ] [ Drone {} ] uses {implementation}",
+            "Network Initializer".green(),
+            drone.id
+        );
+        implementations.insert(drone.id, implementation);
+    }
+
+    Ok(implementations)
+}
+
+/// Every implementation of the default order once, in that order
+fn distinct_implementations() -> Vec<&'static str> {
+    let mut names = Vec::new();
+    for name in registry::default_drone_order() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// How many drones use each implementation, and which ones no drone uses
+pub fn coverage_report(implementations: &FactoryAssignment) -> String {
+    let names = distinct_implementations();
+    let mut report = String::new();
+
+    // `write!` on a String can't fail
+    for name in &names {
+        let count = implementations
+            .values()
+            .filter(|implementation| implementation == name)
+            .count();
+        let _ = writeln!(report, "{name:>16} {count:>3} drone(s)");
+    }
+
+    let unused: Vec<&str> = names
+        .into_iter()
+        .filter(|name| !implementations.values().any(|used| used == name))
+        .collect();
+    if unused.is_empty() {
+        report.push_str("Every implementation is used\n");
+    } else {
+        let _ = writeln!(report, "Unused: {}", unused.join(", "));
+    }
+    report
+}
+
+/// Kind getting the servers left over by the thirds, unless the options
+/// say otherwise. The GUI guesses the kinds this way too.
+pub const DEFAULT_SERVER_REMAINDER: ServerKind = ServerKind::Text;
+
+/// How many of `servers` servers get each kind by position, in config order:
+/// text servers first, then media servers, then communication servers. Each
+/// kind gets a third rounded down, and `remainder` also gets the one or two
+/// servers left over, so 4 servers give text 2, media 1 and communication 1
+/// with the text remainder, and 5 give text 1, media 1 and communication 3
+/// with the communication one.
+pub fn positional_server_split(servers: usize, remainder: ServerKind) -> [(ServerKind, usize); 3] {
+    let third = servers / 3;
+    [
+        ServerKind::Text,
+        ServerKind::Media,
+        ServerKind::Communication,
+    ]
+    .map(|kind| {
+        let extra = if kind == remainder { servers % 3 } else { 0 };
+        (kind, third + extra)
+    })
+}
+
+/// Kind of the server at `position` in the config when it has no explicit
+/// `kind`, following `split`
+fn positional_server_kind(position: usize, split: &[(ServerKind, usize); 3]) -> ServerKind {
+    let mut end = 0;
+    for (kind, count) in split {
+        end += count;
+        if position < end {
+            return *kind;
+        }
+    }
+    // Past the last server, only reached with a wrong split
+    ServerKind::Communication
+}
+
+/// Kind of a client without an explicit `kind`, `count` goes up from zero:
+/// the first half are chat clients and the rest media clients
+fn positional_client_kind(count: usize, half: usize) -> ClientKind {
+    if count < half {
+        ClientKind::Chat
+    } else {
+        ClientKind::Media
+    }
+}
+
+/// Kind of every server of the config, explicit or positional.
+///
+/// Channels and servers are both built from this map, so a server always
+/// gets the channels matching its constructor.
+pub fn classify_servers(network_config: &NetworkConfig) -> HashMap<NodeId, ServerKind> {
+    let servers = &network_config.config.server;
+    let split = positional_server_split(servers.len(), DEFAULT_SERVER_REMAINDER);
+    servers
+        .iter()
+        .enumerate()
+        .map(|(position, server)| {
+            let kind = network_config
+                .server_kinds
+                .get(&server.id)
+                .copied()
+                .unwrap_or_else(|| positional_server_kind(position, &split));
+            (server.id, kind)
+        })
+        .collect()
+}
+
+/// Kind of every client of the config, explicit or positional.
+///
+/// Like [`classify_servers`], computed once so channels and constructors
+/// can't disagree.
+pub fn classify_clients(network_config: &NetworkConfig) -> HashMap<NodeId, ClientKind> {
+    let clients = &network_config.config.client;
+    clients
+        .iter()
+        .enumerate()
+        .map(|(position, client)| {
+            let kind = network_config
+                .client_kinds
+                .get(&client.id)
+                .copied()
+                .unwrap_or_else(|| positional_client_kind(position, clients.len() / 2));
+            (client.id, kind)
+        })
+        .collect()
+}
+
+/// Config path meaning the config is read from the standard input
+pub const STDIN_PATH: &str = "-";
+
+/// Config read when no path is given, the one shipped with the crate
+pub const DEFAULT_CONFIG_PATH: &str = "src/config.toml";
+
+/// Read and parse the config file at `path`
+pub fn open(path: &Path) -> Result<NetworkConfig, NetworkInitError> {
+    open_as(path, None, false)
+}
+
+/// Read and parse the config file at `path`, or the standard input when
+/// `path` is [`STDIN_PATH`]. Written in `format`, or else in the format
+/// given by the extension, TOML for the standard input. With `expand_env`,
+/// the `${VAR}` references are replaced first, see [`config::expand_env`].
+pub fn open_as(
+    path: &Path,
+    format: Option<ConfigFormat>,
+    expand_env: bool,
+) -> Result<NetworkConfig, NetworkInitError> {
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+    let config_data = read_config(path, format, expand_env)?;
+    config::parse(format, &config_data).map_err(|source| parse_error(path, &config_data, source))
+}
+
+/// The config of the files in `options`, the one shipped with the crate
+/// when there are none
+fn load_options_config(options: &RunOptions) -> Result<NetworkConfig, NetworkInitError> {
+    if options.config_paths.is_empty() {
+        load_configs(
+            &[DEFAULT_CONFIG_PATH],
+            options.config_format,
+            options.expand_env,
+        )
+    } else {
+        load_configs(
+            &options.config_paths,
+            options.config_format,
+            options.expand_env,
+        )
+    }
+}
+
+/// Read and merge the config fragments at `paths` into a single config, as
+/// [`config::parse_fragments`] does. Each is written in `format`, or else in
+/// the format given by its extension, and has its `${VAR}` references
+/// replaced with `expand_env`. An id defined in two fragments is an error.
+pub fn load_configs(
+    paths: &[impl AsRef<Path>],
+    format: Option<ConfigFormat>,
+    expand_env: bool,
+) -> Result<NetworkConfig, NetworkInitError> {
+    let mut fragments = Vec::with_capacity(paths.len());
+    for path in paths {
+        let path = path.as_ref();
+        let fragment_format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+        fragments.push((
+            fragment_format,
+            read_config(path, fragment_format, expand_env)?,
+        ));
+    }
+    let network_config = config::parse_fragments(
+        fragments
+            .iter()
+            .map(|(format, data)| (*format, data.as_slice())),
+    )
+    .map_err(|source| {
+        // The merged parse doesn't say which fragment failed, only then is
+        // each one parsed alone to find it
+        paths
+            .iter()
+            .zip(&fragments)
+            .find_map(|(path, (format, data))| {
+                let source = config::parse(*format, data).err()?;
+                Some(parse_error(path.as_ref(), data, source))
+            })
+            .unwrap_or_else(|| parse_error(Path::new("<fragments>"), &[], source))
+    })?;
+    validation::validate_unique_ids(&network_config.config)?;
+    Ok(network_config)
+}
 
-fn drone_factory<T>() -> Box<DroneFactoryFn>
+/// Content of the config file at `path`, or of the standard input when
+/// `path` is [`STDIN_PATH`], with its `${VAR}` references replaced when
+/// `expand_env` is set. A binary config is never expanded.
+fn read_config(
+    path: &Path,
+    format: ConfigFormat,
+    expand_env: bool,
+) -> Result<Vec<u8>, NetworkInitError> {
+    let config_data = if path == Path::new(STDIN_PATH) {
+        let mut config_data = Vec::new();
+        io::stdin()
+            .read_to_end(&mut config_data)
+            .map_err(|source| NetworkInitError::Io {
+                path: PathBuf::from("<stdin>"),
+                source,
+            })?;
+        config_data
+    } else {
+        // Read content of the config file, reporting the full path on failure
+        fs::read(path).map_err(|source| NetworkInitError::Io {
+            path: std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+            source,
+        })?
+    };
+    if !expand_env || format == ConfigFormat::Binary {
+        return Ok(config_data);
+    }
+    let text = std::str::from_utf8(&config_data)
+        .map_err(|source| parse_error(path, &config_data, ParseError::Utf8(source)))?;
+    let expanded = config::expand_env(text).map_err(|source| NetworkInitError::ExpandEnv {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(expanded.into_bytes())
+}
+
+/// Write the config at `input`, in `format` or else in the format given by
+/// its extension, to `output` in the binary format
+pub fn convert(
+    input: &Path,
+    format: Option<ConfigFormat>,
+    output: &Path,
+) -> Result<(), NetworkInitError> {
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(input));
+    let config_data = read_config(input, format, false)?;
+    let binary = config::to_binary(format, &config_data)
+        .map_err(|source| parse_error(input, &config_data, source))?;
+    write_output(output, binary)
+}
+
+/// `source`, the error parsing `data` read from `path`, with where in `data`
+/// it happened
+fn parse_error(path: &Path, data: &[u8], source: ParseError) -> NetworkInitError {
+    let path = if path == Path::new(STDIN_PATH) {
+        PathBuf::from("<stdin>")
+    } else {
+        path.to_path_buf()
+    };
+    NetworkInitError::Parse {
+        path,
+        location: source.locate(data),
+        source,
+    }
+}
+
+/// Spawn a thread with the given name, so it can be told apart in panic
+/// messages and debuggers
+pub(crate) fn spawn_named<F, T>(name: String, f: F) -> Result<JoinHandle<T>, NetworkInitError>
 where
-    T: Drone + 'static,
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
 {
-    Box::new(
-        |drone, event_send, command_recv_hashmap, packet_send_hashmap, packet_recv_hashmap| {
-            // Get drone's packet receiver channel
-            if let Some(packet_recv) = packet_recv_hashmap.get(&drone.id) {
-                // Create packet send hashmap
-                let mut packet_send = HashMap::<NodeId, Sender<Packet>>::new();
-                // Fill hashmap with only neighbor
-                for neighbor in &drone.connected_node_ids {
-                    packet_send_hashmap
-                        .iter()
-                        .filter(|(node_id, _)| *node_id == neighbor)
-                        .for_each(|(node_id, channel)| {
-                            packet_send.insert(*node_id, channel.clone());
-                        });
-                }
+    thread::Builder::new()
+        .name(name.clone())
+        .spawn(f)
+        .map_err(|source| NetworkInitError::Spawn {
+            thread: name,
+            source,
+        })
+}
 
-                // Get drone's command receiver channel
-                if let Some(command_recv) = command_recv_hashmap.get(&drone.id) {
-                    Box::new(T::new(
-                        drone.id,
-                        event_send.clone(),
-                        command_recv.clone(),
-                        packet_recv.clone(),
-                        packet_send,
-                        drone.pdr,
-                    ))
-                } else {
-                    panic!("Command receiver not found for drone {}", drone.id);
-                }
-            } else {
-                panic!("Packet receiver not found for drone {}", drone.id);
+/// Give every drone of `config` a pdr drawn uniformly from `pdr_range`, if
+/// any, with `seed` or else from the OS entropy
+fn apply_pdr_range(
+    config: &mut Config,
+    pdr_range: Option<(f32, f32)>,
+    seed: Option<u64>,
+) -> Result<(), NetworkInitError> {
+    let Some((min, max)) = pdr_range else {
+        return Ok(());
+    };
+    check_pdr_range(min, max)?;
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    for drone in &mut config.drone {
+        drone.pdr = rng.random_range(min..=max);
+        info!(
+            "[ {} ] Drone {} gets pdr {}",
+            "Network Initializer".green(),
+            drone.id,
+            drone.pdr
+        );
+    }
+    Ok(())
+}
+
+/// Whether `0.0 <= min <= max <= 1.0`
+fn check_pdr_range(min: f32, max: f32) -> Result<(), NetworkInitError> {
+    if 0.0 <= min && min <= max && max <= 1.0 {
+        Ok(())
+    } else {
+        Err(NetworkInitError::InvalidPdrRange { min, max })
+    }
+}
+
+/// Draw the kind of every server of `network_config` without an explicit
+/// one from `server_mix`, if any, with `seed` or else from the OS entropy.
+/// The kinds drawn become explicit, so the channels and the servers are
+/// built alike.
+pub fn apply_server_mix(
+    network_config: &mut NetworkConfig,
+    server_mix: Option<(f64, f64, f64)>,
+    seed: Option<u64>,
+) -> Result<(), NetworkInitError> {
+    let Some((communication, text, media)) = server_mix else {
+        return Ok(());
+    };
+    let weights = [
+        (ServerKind::Communication, communication),
+        (ServerKind::Text, text),
+        (ServerKind::Media, media),
+    ];
+    let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+    let valid = weights
+        .iter()
+        .all(|(_, weight)| weight.is_finite() && *weight >= 0.0);
+    if !valid || !total.is_normal() {
+        return Err(NetworkInitError::InvalidServerMix {
+            communication,
+            text,
+            media,
+        });
+    }
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    for server in &network_config.config.server {
+        if network_config.server_kinds.contains_key(&server.id) {
+            continue;
+        }
+        let mut draw = rng.random_range(0.0..total);
+        // Rounding can leave a bit of the draw past the last weight
+        let mut kind = ServerKind::Media;
+        for (candidate, weight) in weights {
+            if weight > 0.0 {
+                kind = candidate;
             }
-        },
-    )
+            if draw < weight {
+                break;
+            }
+            draw -= weight;
+        }
+        info!(
+            "[ {} ] Server {} gets kind {kind:?}",
+            "Network Initializer".green(),
+            server.id
+        );
+        network_config.server_kinds.insert(server.id, kind);
+    }
+    Ok(())
 }
 
-fn open(path: &str) -> Config {
-    // Read content of file src/config.toml
-    let config_data = fs::read_to_string(path).expect("Unable to read config file");
-    // Parse previously created string
-    toml::from_str(&config_data).expect("Unable to parse TOML")
+/// Restrict the servers of `network_config` to `allowed`, if any: an
+/// explicit kind must be one of them, and the servers without one are split
+/// evenly among them, the first ones getting the first kind. The kinds given
+/// become explicit, like the drawn ones of [`apply_server_mix`].
+pub fn apply_server_kinds(
+    network_config: &mut NetworkConfig,
+    allowed: Option<&[ServerKind]>,
+) -> Result<(), NetworkInitError> {
+    let Some(allowed) = allowed else {
+        return Ok(());
+    };
+    let servers = &network_config.config.server;
+    if allowed.is_empty() && !servers.is_empty() {
+        return Err(NetworkInitError::NoServerKinds);
+    }
+    for server in servers {
+        if let Some(kind) = network_config.server_kinds.get(&server.id) {
+            if !allowed.contains(kind) {
+                return Err(NetworkInitError::ExcludedServerKind {
+                    server: server.id,
+                    kind: *kind,
+                });
+            }
+        }
+    }
+
+    let unassigned: Vec<NodeId> = servers
+        .iter()
+        .map(|server| server.id)
+        .filter(|id| !network_config.server_kinds.contains_key(id))
+        .collect();
+    for (position, id) in unassigned.iter().enumerate() {
+        let kind = allowed[position * allowed.len() / unassigned.len()];
+        network_config.server_kinds.insert(*id, kind);
+    }
+    Ok(())
 }
 
-#[allow(clippy::too_many_lines)]
-pub fn run() {
+/// Split the servers of `network_config` without an explicit `kind` by
+/// position, giving the servers left over by the thirds to `remainder`, if
+/// any, see [`positional_server_split`]. The split still counts every server,
+/// and the kinds given become explicit like those of [`apply_server_kinds`].
+pub fn apply_server_remainder(network_config: &mut NetworkConfig, remainder: Option<ServerKind>) {
+    let Some(remainder) = remainder else {
+        return;
+    };
+    let servers = &network_config.config.server;
+    let split = positional_server_split(servers.len(), remainder);
+    let positional: Vec<(NodeId, ServerKind)> = servers
+        .iter()
+        .enumerate()
+        .filter(|(_, server)| !network_config.server_kinds.contains_key(&server.id))
+        .map(|(position, server)| (server.id, positional_server_kind(position, &split)))
+        .collect();
+    network_config.server_kinds.extend(positional);
+}
+
+/// Give every drone of `config` the pdr `pdr_override`, if any
+fn apply_pdr_override(
+    config: &mut Config,
+    pdr_override: Option<f32>,
+) -> Result<(), NetworkInitError> {
+    if let Some(pdr) = pdr_override {
+        if !(0.0..=1.0).contains(&pdr) {
+            return Err(NetworkInitError::InvalidPdrOverride(pdr));
+        }
+        info!(
+            "[ {} ] Overriding the pdr of every drone with {pdr}",
+            "Network Initializer".green()
+        );
+        for drone in &mut config.drone {
+            drone.pdr = pdr;
+        }
+    }
+    Ok(())
+}
+
+/// Drop the clients and servers `options` leaves out from `network_config`,
+/// together with the edges drones have to them, so the rest is validated
+/// and built as if they were never in the config
+pub(crate) fn omit_nodes(network_config: &mut NetworkConfig, options: &RunOptions) {
+    let mut omitted = Vec::new();
+    if options.no_clients {
+        omitted.extend(network_config.config.client.drain(..).map(|c| c.id));
+        network_config.client_kinds.clear();
+        network_config.client_implementations.clear();
+    }
+    if options.no_servers {
+        omitted.extend(network_config.config.server.drain(..).map(|s| s.id));
+        network_config.server_kinds.clear();
+        network_config.server_implementations.clear();
+    }
+    if omitted.is_empty() {
+        return;
+    }
+
+    for commands in network_config.init_commands.values_mut() {
+        commands.retain(|command| !matches!(command.node(), Some(node) if omitted.contains(&node)));
+    }
+
+    for drone in &mut network_config.config.drone {
+        drone.connected_node_ids.retain(|neighbor| {
+            let kept = !omitted.contains(neighbor);
+            if !kept {
+                warn!(
+                    "[ {} ] [ Drone {} ] loses its neighbor {neighbor}, which is left out",
+                    "Network Initializer".yellow(),
+                    drone.id
+                );
+            }
+            kept
+        });
+    }
+}
+
+/// Fail in strict mode, warn otherwise, when some nodes can't take part in
+/// the simulation
+fn check_connectivity(topology: &Topology, strict: bool) -> Result<(), NetworkInitError> {
+    if let Err(problems) = validation::validate_connectivity(topology) {
+        if strict {
+            return Err(problems.into());
+        }
+        for problem in problems {
+            warn!("[ {} ] {problem}", "Network Initializer".yellow());
+        }
+    }
+    Ok(())
+}
+
+/// Do every check and decision `run` would, then print what would be built
+fn dry_run(
+    mut network_config: NetworkConfig,
+    options: &RunOptions,
+    registries: &Registries,
+) -> Result<(), NetworkInitError> {
+    apply_pdr_range(
+        &mut network_config.config,
+        options.pdr_range,
+        options.pdr_seed,
+    )?;
+    apply_pdr_override(&mut network_config.config, options.pdr_override)?;
+
+    let implementations = choose_implementations(&network_config, options)?;
+    for drone in &network_config.config.drone {
+        let implementation = channel(&implementations, drone.id, "Implementation")?;
+        if !registries.drones.contains(implementation) {
+            return Err(NetworkInitError::UnknownDroneImplementation {
+                drone: drone.id,
+                implementation: implementation.clone(),
+            });
+        }
+    }
+
+    // Same lookups as when the clients and servers are built
+    let client_kinds = classify_clients(&network_config);
+    for (id, implementation) in client_implementations(&network_config, &client_kinds) {
+        let kind = client_kinds[&id];
+        if !registries.clients.contains(kind, &implementation) {
+            return Err(NetworkInitError::UnknownNodeImplementation {
+                node: id,
+                kind: format!("{kind} client"),
+                implementation,
+            });
+        }
+    }
+    let server_kinds = classify_servers(&network_config);
+    for (id, implementation) in server_implementations(&network_config, &server_kinds) {
+        let kind = server_kinds[&id];
+        if !registries.servers.contains(kind, &implementation) {
+            return Err(NetworkInitError::UnknownNodeImplementation {
+                node: id,
+                kind: format!("{kind} server"),
+                implementation,
+            });
+        }
+    }
+
+    check_connectivity(
+        &build_topology(&network_config.config),
+        options.strict_connectivity,
+    )?;
+
     info!(
-        "[ {} ] Starting Network Initializer",
+        "[ {} ] Dry run, nothing is spawned",
         "Network Initializer".green()
     );
-    // Open and read File
-    let config = open("src/config.toml");
+    print!("{}", plan::summary(&network_config, &implementations));
+    Ok(())
+}
+
+/// Write a file produced by the initializer
+fn write_output(path: &Path, content: impl AsRef<[u8]>) -> Result<(), NetworkInitError> {
+    fs::write(path, content).map_err(|source| NetworkInitError::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
 
+/// Packet senders of the `neighbors` of client or server `node`, from
+/// `packet_send`. A node listing itself or a neighbor that isn't one of
+/// `drones` is rejected by the validation, but a network built without it
+/// must not get its own sender or a direct link between endpoints: that
+/// entry is skipped.
+pub(crate) fn neighbor_senders(
+    node: NodeId,
+    neighbors: &[NodeId],
+    packet_send: &HashMap<NodeId, Sender<Packet>>,
+    drones: &HashSet<NodeId>,
+) -> Result<HashMap<NodeId, Sender<Packet>>, NetworkInitError> {
+    let mut senders = HashMap::new();
+    for neighbor in neighbors {
+        if *neighbor == node {
+            warn_self_loop(node);
+            continue;
+        }
+        if !drones.contains(neighbor) {
+            warn!(
+                "[ {} ] Node {node} lists {neighbor} as a neighbor, which is not a drone, skipping it",
+                "Network Initializer".yellow()
+            );
+            continue;
+        }
+        senders.insert(
+            *neighbor,
+            channel(packet_send, *neighbor, "Packet sender")?.clone(),
+        );
+    }
+    Ok(senders)
+}
+
+/// Warn that `node` lists itself as a neighbor and won't get its own sender
+pub(crate) fn warn_self_loop(node: NodeId) {
+    warn!(
+        "[ {} ] Node {node} lists itself as a neighbor, skipping it",
+        "Network Initializer".yellow()
+    );
+}
+
+/// Warn that `drone` got no packet sender at all out of its `neighbors`: it
+/// receives but can't forward anything. Either it has no neighbors, or none
+/// of them got a channel.
+pub(crate) fn warn_no_senders(drone: NodeId, neighbors: &[NodeId]) {
+    warn!(
+        "[ {} ] [ Drone {drone} ] has no neighbor to send to, listed: {neighbors:?}",
+        "Network Initializer".yellow()
+    );
+}
+
+/// Look up the channel registered for `node`, or report which one is missing
+pub(crate) fn channel<'a, T>(
+    map: &'a HashMap<NodeId, T>,
+    node: NodeId,
+    channel: &'static str,
+) -> Result<&'a T, NetworkInitError> {
+    map.get(&node)
+        .ok_or(NetworkInitError::MissingChannel { node, channel })
+}
+
+/// Implementation of every client: the one its entry requests, or the
+/// default one for its kind in `client_kinds`
+pub fn client_implementations(
+    network_config: &NetworkConfig,
+    client_kinds: &HashMap<NodeId, ClientKind>,
+) -> HashMap<NodeId, String> {
+    client_kinds
+        .iter()
+        .map(|(id, kind)| {
+            let implementation = network_config.client_implementations.get(id).map_or(
+                registry::default_client_implementation(*kind),
+                String::as_str,
+            );
+            (*id, implementation.to_string())
+        })
+        .collect()
+}
+
+/// Implementation of every server: the one its entry requests, or the
+/// default one for its kind in `server_kinds`
+pub fn server_implementations(
+    network_config: &NetworkConfig,
+    server_kinds: &HashMap<NodeId, ServerKind>,
+) -> HashMap<NodeId, String> {
+    server_kinds
+        .iter()
+        .map(|(id, kind)| {
+            let implementation = network_config.server_implementations.get(id).map_or(
+                registry::default_server_implementation(*kind),
+                String::as_str,
+            );
+            (*id, implementation.to_string())
+        })
+        .collect()
+}
+
+/// Factory building the client or server `node`: the implementation its
+/// entry requests in `requested`, or `default`, looked up with `lookup`
+fn node_factory<'r, F: ?Sized>(
+    requested: &HashMap<NodeId, String>,
+    node: NodeId,
+    kind: &str,
+    default: &str,
+    lookup: impl Fn(&str) -> Option<&'r F>,
+) -> Result<&'r F, NetworkInitError> {
+    let implementation = requested.get(&node).map_or(default, String::as_str);
+    lookup(implementation).ok_or_else(|| NetworkInitError::UnknownNodeImplementation {
+        node,
+        kind: kind.to_string(),
+        implementation: implementation.to_string(),
+    })
+}
+
+/// Every channel of the network, created but not handed to any node yet.
+///
+/// Packet channels exist for every node, command and event channels for the
+/// node types that have them. The `*_send` maps of clients and servers pair
+/// the command sender with the packet sender, as the simulation controller
+/// expects.
+pub struct Channels {
+    pub packet_send: HashMap<NodeId, Sender<Packet>>,
+    pub packet_recv: HashMap<NodeId, Receiver<Packet>>,
+
+    pub command_send: HashMap<NodeId, Sender<DroneCommand>>,
+    pub command_recv: HashMap<NodeId, Receiver<DroneCommand>>,
+    pub event_send: Sender<DroneEvent>,
+    pub event_recv: Receiver<DroneEvent>,
+
+    pub comm_server_send: HashMap<NodeId, (Sender<CommunicationServerCommand>, Sender<Packet>)>,
+    pub comm_server_recv: HashMap<NodeId, Receiver<CommunicationServerCommand>>,
+    pub comm_server_event_send: Sender<CommunicationServerEvent>,
+    pub comm_server_event_recv: Receiver<CommunicationServerEvent>,
+
+    pub text_server_send: HashMap<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>,
+    pub text_server_recv: HashMap<NodeId, Receiver<ContentServerCommand>>,
+    pub text_server_event_send: Sender<ContentServerEvent>,
+    pub text_server_event_recv: Receiver<ContentServerEvent>,
+
+    pub media_server_send: HashMap<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>,
+    pub media_server_recv: HashMap<NodeId, Receiver<ContentServerCommand>>,
+    pub media_server_event_send: Sender<ContentServerEvent>,
+    pub media_server_event_recv: Receiver<ContentServerEvent>,
+    /// Kind every server's channels were built for
+    pub server_kinds: HashMap<NodeId, ServerKind>,
+
+    pub cclient_send: HashMap<NodeId, (Sender<ChatClientCommand>, Sender<Packet>)>,
+    pub cclient_recv: HashMap<NodeId, Receiver<ChatClientCommand>>,
+    pub cclient_event_send: Sender<ChatClientEvent>,
+    pub cclient_event_recv: Receiver<ChatClientEvent>,
+
+    pub mclient_send: HashMap<NodeId, (Sender<MediaClientCommand>, Sender<Packet>)>,
+    pub mclient_recv: HashMap<NodeId, Receiver<MediaClientCommand>>,
+    pub mclient_event_send: Sender<MediaClientEvent>,
+    pub mclient_event_recv: Receiver<MediaClientEvent>,
+    /// Kind every client's channels were built for
+    pub client_kinds: HashMap<NodeId, ClientKind>,
+}
+
+/// Packet channel of one node, bounded when `capacity` is given
+pub(crate) fn packet_channel(capacity: Option<usize>) -> (Sender<Packet>, Receiver<Packet>) {
+    match capacity {
+        Some(capacity) => bounded::<Packet>(capacity),
+        None => unbounded::<Packet>(),
+    }
+}
+
+/// Put a relay between every node and its packet channel: the node gets the
+/// receiver of a new inner channel, the relay the original one and the inner
+/// sender. The senders handed to the neighbors are left as they are.
+fn add_relays(
+    packet_recv: HashMap<NodeId, Receiver<Packet>>,
+    capacity: Option<usize>,
+) -> (
+    HashMap<NodeId, Receiver<Packet>>,
+    Vec<(NodeId, Receiver<Packet>, Sender<Packet>)>,
+) {
+    let mut inner = HashMap::new();
+    let mut relays = Vec::new();
+    for (id, outer_recv) in packet_recv {
+        let (inner_send, inner_recv) = packet_channel(capacity);
+        inner.insert(id, inner_recv);
+        relays.push((id, outer_recv, inner_send));
+    }
+    (inner, relays)
+}
+
+/// Log the kind and implementation of every node of `kinds`, sorted by id
+fn log_nodes<K: fmt::Debug>(
+    kinds: &HashMap<NodeId, K>,
+    implementations: &HashMap<NodeId, String>,
+    node: &str,
+) {
+    let mut ids: Vec<&NodeId> = kinds.keys().collect();
+    ids.sort_unstable();
+    for id in ids {
+        let implementation = implementations.get(id).map_or("", String::as_str);
+        trace!(
+            "[ {} ] Created {:?} {node} {id} ({implementation})",
+            "Network Initializer".green(),
+            kinds[id]
+        );
+    }
+}
+
+/// Pause the relays behind `shutdown`, warning when there are none
+pub(crate) fn pause_relays(shutdown: &Shutdown, pausable: bool) {
+    if !pausable {
+        warn!(
+            "[ {} ] The network was built without relays, it can't be paused",
+            "Network Initializer".yellow()
+        );
+        return;
+    }
+    shutdown.pause_switch().pause();
+    info!("[ {} ] Network paused", "Network Initializer".green());
+}
+
+/// Create the channels of every node of `network_config`, before any node
+/// exists. Packet channels hold at most `channel_capacity` packets when
+/// given, command and event channels are always unbounded.
+#[allow(clippy::too_many_lines)]
+pub fn build_channels(network_config: &NetworkConfig, channel_capacity: Option<usize>) -> Channels {
     // Packet channels
     let mut packet_send = HashMap::<NodeId, Sender<Packet>>::new();
     let mut packet_recv = HashMap::<NodeId, Receiver<Packet>>::new();
 
     // Drones
-    let mut drones: Vec<Box<dyn Drone>> = Vec::new();
     let mut command_send = HashMap::<NodeId, Sender<DroneCommand>>::new();
     let mut command_recv = HashMap::<NodeId, Receiver<DroneCommand>>::new();
 
     let (event_send, event_recv) = unbounded::<DroneEvent>();
 
     // Fill drone channels
-    for drone in &config.drone {
+    for drone in &network_config.config.drone {
         let id = drone.id;
 
-        let (pkt_send, pkt_recv) = unbounded::<Packet>();
+        let (pkt_send, pkt_recv) = packet_channel(channel_capacity);
         packet_send.insert(id, pkt_send);
         packet_recv.insert(id, pkt_recv);
 
@@ -119,7 +1208,6 @@ pub fn run() {
     }
 
     // Communication Servers
-    let mut communication_servers = Vec::<CommunicationServer>::new();
     let mut comm_server_send =
         HashMap::<NodeId, (Sender<CommunicationServerCommand>, Sender<Packet>)>::new();
     let mut comm_server_recv = HashMap::<NodeId, Receiver<CommunicationServerCommand>>::new();
@@ -127,7 +1215,6 @@ pub fn run() {
     let (comm_server_event_send, comm_server_event_recv) = unbounded::<CommunicationServerEvent>();
 
     // TextContentServers
-    let mut text_servers = Vec::<ContentServer>::new();
     let mut text_server_send =
         HashMap::<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>::new();
     let mut text_server_recv = HashMap::<NodeId, Receiver<ContentServerCommand>>::new();
@@ -135,7 +1222,6 @@ pub fn run() {
     let (text_server_event_send, text_server_event_recv) = unbounded::<ContentServerEvent>();
 
     // MediaContentServers
-    let mut media_servers = Vec::<ContentServer>::new();
     let mut media_server_send =
         HashMap::<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>::new();
     let mut media_server_recv = HashMap::<NodeId, Receiver<ContentServerCommand>>::new();
@@ -143,372 +1229,2045 @@ pub fn run() {
     let (media_server_event_send, media_server_event_recv) = unbounded::<ContentServerEvent>();
 
     // Fill servers channels
-    let third = config.server.len() / 3;
-    let mut count = config.server.len();
-    for server in &config.server {
-        if count > (third * 2) {
-            // TextContentServer
-            let (text_server_command_send, text_server_command_recv) =
-                unbounded::<ContentServerCommand>();
-            let (pkt_send, pkt_recv) = unbounded::<Packet>();
-
-            packet_send.insert(server.id, pkt_send.clone());
-            packet_recv.insert(server.id, pkt_recv);
-
-            text_server_recv.insert(server.id, text_server_command_recv.clone());
-            text_server_send.insert(server.id, (text_server_command_send, pkt_send));
-        } else if count > third {
-            // MediaContentServer
-            let (media_server_command_send, media_server_command_recv) =
-                unbounded::<ContentServerCommand>();
-            let (pkt_send, pkt_recv) = unbounded::<Packet>();
-
-            packet_send.insert(server.id, pkt_send.clone());
-            packet_recv.insert(server.id, pkt_recv);
-
-            media_server_recv.insert(server.id, media_server_command_recv.clone());
-            media_server_send.insert(server.id, (media_server_command_send, pkt_send));
-        } else {
-            // CommunicationContentServer
-            let (comm_server_command_send, comm_server_command_recv) =
-                unbounded::<CommunicationServerCommand>();
-            let (pkt_send, pkt_recv) = unbounded::<Packet>();
+    let server_kinds = classify_servers(network_config);
+    for server in &network_config.config.server {
+        match server_kinds[&server.id] {
+            ServerKind::Text => {
+                // TextContentServer
+                let (text_server_command_send, text_server_command_recv) =
+                    unbounded::<ContentServerCommand>();
+                let (pkt_send, pkt_recv) = packet_channel(channel_capacity);
+
+                packet_send.insert(server.id, pkt_send.clone());
+                packet_recv.insert(server.id, pkt_recv);
+
+                text_server_recv.insert(server.id, text_server_command_recv.clone());
+                text_server_send.insert(server.id, (text_server_command_send, pkt_send));
+            }
+            ServerKind::Media => {
+                // MediaContentServer
+                let (media_server_command_send, media_server_command_recv) =
+                    unbounded::<ContentServerCommand>();
+                let (pkt_send, pkt_recv) = packet_channel(channel_capacity);
 
-            packet_send.insert(server.id, pkt_send.clone());
-            packet_recv.insert(server.id, pkt_recv);
+                packet_send.insert(server.id, pkt_send.clone());
+                packet_recv.insert(server.id, pkt_recv);
 
-            comm_server_recv.insert(server.id, comm_server_command_recv.clone());
-            comm_server_send.insert(server.id, (comm_server_command_send, pkt_send));
-        }
+                media_server_recv.insert(server.id, media_server_command_recv.clone());
+                media_server_send.insert(server.id, (media_server_command_send, pkt_send));
+            }
+            ServerKind::Communication => {
+                // CommunicationContentServer
+                let (comm_server_command_send, comm_server_command_recv) =
+                    unbounded::<CommunicationServerCommand>();
+                let (pkt_send, pkt_recv) = packet_channel(channel_capacity);
 
-        count -= 1;
+                packet_send.insert(server.id, pkt_send.clone());
+                packet_recv.insert(server.id, pkt_recv);
+
+                comm_server_recv.insert(server.id, comm_server_command_recv.clone());
+                comm_server_send.insert(server.id, (comm_server_command_send, pkt_send));
+            }
+        }
     }
 
     // ChatClients
-    let mut chat_clients = Vec::<ChatClient>::new();
     let mut cclient_send = HashMap::<NodeId, (Sender<ChatClientCommand>, Sender<Packet>)>::new();
     let mut cclient_recv = HashMap::<NodeId, Receiver<ChatClientCommand>>::new();
 
     let (cclient_event_send, cclient_event_recv) = unbounded::<ChatClientEvent>();
 
     // MediaClient
-    let mut media_clients = Vec::<MediaClient>::new();
     let mut mclient_send = HashMap::<NodeId, (Sender<MediaClientCommand>, Sender<Packet>)>::new();
     let mut mclient_recv = HashMap::<NodeId, Receiver<MediaClientCommand>>::new();
 
     let (mclient_event_send, mclient_event_recv) = unbounded::<MediaClientEvent>();
 
     // Fill the client server
-    let half = config.client.len() / 2;
-    count = 0;
-    for client in &config.client {
-        if count < half {
-            // ChatClient
-            let (cclient_command_send, cclient_command_recv) = unbounded::<ChatClientCommand>();
-            let (pkt_send, pkt_recv) = unbounded::<Packet>();
-
-            packet_send.insert(client.id, pkt_send.clone());
-            packet_recv.insert(client.id, pkt_recv);
-
-            cclient_recv.insert(client.id, cclient_command_recv);
-            cclient_send.insert(client.id, (cclient_command_send, pkt_send));
+    let client_kinds = classify_clients(network_config);
+    for client in &network_config.config.client {
+        match client_kinds[&client.id] {
+            ClientKind::Chat => {
+                // ChatClient
+                let (cclient_command_send, cclient_command_recv) = unbounded::<ChatClientCommand>();
+                let (pkt_send, pkt_recv) = packet_channel(channel_capacity);
+
+                packet_send.insert(client.id, pkt_send.clone());
+                packet_recv.insert(client.id, pkt_recv);
+
+                cclient_recv.insert(client.id, cclient_command_recv);
+                cclient_send.insert(client.id, (cclient_command_send, pkt_send));
+            }
+            ClientKind::Media => {
+                // Media Client
+                let (mclient_command_send, mclient_command_recv) =
+                    unbounded::<MediaClientCommand>();
+                let (pkt_send, pkt_recv) = packet_channel(channel_capacity);
+
+                packet_send.insert(client.id, pkt_send.clone());
+                packet_recv.insert(client.id, pkt_recv);
+
+                mclient_recv.insert(client.id, mclient_command_recv);
+                mclient_send.insert(client.id, (mclient_command_send, pkt_send));
+            }
+        }
+    }
+
+    Channels {
+        packet_send,
+        packet_recv,
+        command_send,
+        command_recv,
+        event_send,
+        event_recv,
+        comm_server_send,
+        comm_server_recv,
+        comm_server_event_send,
+        comm_server_event_recv,
+        text_server_send,
+        text_server_recv,
+        text_server_event_send,
+        text_server_event_recv,
+        media_server_send,
+        media_server_recv,
+        media_server_event_send,
+        media_server_event_recv,
+        server_kinds,
+        cclient_send,
+        cclient_recv,
+        cclient_event_send,
+        cclient_event_recv,
+        mclient_send,
+        mclient_recv,
+        mclient_event_send,
+        mclient_event_recv,
+        client_kinds,
+    }
+}
+
+/// The whole network, built from the config but not running yet.
+///
+/// Every node is already constructed and wired to its neighbors, so the
+/// topology can be inspected before [`Network::spawn`] starts the threads.
+pub struct Network {
+    pub config: Config,
+
+    // Every node together with its id
+    pub drones: Vec<(NodeId, Box<dyn Drone>)>,
+    pub chat_clients: Vec<(NodeId, Box<dyn Node>)>,
+    pub media_clients: Vec<(NodeId, Box<dyn Node>)>,
+    pub communication_servers: Vec<(NodeId, Box<dyn Node>)>,
+    pub text_servers: Vec<(NodeId, Box<dyn Node>)>,
+    pub media_servers: Vec<(NodeId, Box<dyn Node>)>,
+
+    /// Neighbors of every node, as given to the simulation controller
+    pub topology: Topology,
+    /// The same, following the drone commands once the network runs
+    pub live_topology: LiveTopology,
+
+    // Channels handed to the simulation controller
+    pub drone_send: HashMap<NodeId, (Sender<DroneCommand>, Sender<Packet>)>,
+    pub event_send: Sender<DroneEvent>,
+    pub event_recv: Receiver<DroneEvent>,
+    pub cclient_send: HashMap<NodeId, (Sender<ChatClientCommand>, Sender<Packet>)>,
+    pub cclient_event_recv: Receiver<ChatClientEvent>,
+    pub mclient_send: HashMap<NodeId, (Sender<MediaClientCommand>, Sender<Packet>)>,
+    pub mclient_event_recv: Receiver<MediaClientEvent>,
+    pub comm_server_send: HashMap<NodeId, (Sender<CommunicationServerCommand>, Sender<Packet>)>,
+    pub comm_server_event_recv: Receiver<CommunicationServerEvent>,
+    pub text_server_send: HashMap<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>,
+    pub text_server_event_recv: Receiver<ContentServerEvent>,
+    pub media_server_send: HashMap<NodeId, (Sender<ContentServerCommand>, Sender<Packet>)>,
+    pub media_server_event_recv: Receiver<ContentServerEvent>,
+
+    // Decisions taken while building the nodes
+    pub implementations: FactoryAssignment,
+    pub client_kinds: HashMap<NodeId, ClientKind>,
+    pub server_kinds: HashMap<NodeId, ServerKind>,
+    pub client_implementations: HashMap<NodeId, String>,
+    pub server_implementations: HashMap<NodeId, String>,
+
+    /// Packets sent and dropped by every drone, filled once the network runs
+    pub stats: StatsTable,
+    /// Packets sent by all the drones together, filled once the network runs
+    pub packets_sent: PacketCounter,
+    /// How long building the drones, clients and servers took
+    pub timings: StartupTimings,
+    /// Restarts panicked drones, when enabled in the options
+    pub supervisor: Option<DroneSupervisor>,
+    /// Adds and removes drones when the config changes, when enabled in the
+    /// options
+    pub reloader: Option<ConfigReloader>,
+
+    shutdown: Shutdown,
+    throughput_meter: bool,
+    metrics_port: Option<u16>,
+    event_observer: Option<EventObserver>,
+    // Outer packet receiver and inner sender of every node, when pausable
+    relays: Vec<(NodeId, Receiver<Packet>, Sender<Packet>)>,
+    links: Links,
+    taps: Subscribers,
+    event_taps: EventTaps,
+    on_ready: Option<ReadyCallback>,
+    controller: ControllerFactory,
+    fault_injector: Option<FaultInjector>,
+    // Sent to the drones once every node runs
+    init_commands: Vec<(NodeId, DroneCommand)>,
+}
+
+/// Join handles of the node threads of a running network, with the id of
+/// the node running on each thread
+pub struct NodeHandles {
+    pub drones: Vec<(NodeId, JoinHandle<()>)>,
+    pub chat_clients: Vec<(NodeId, JoinHandle<()>)>,
+    pub media_clients: Vec<(NodeId, JoinHandle<()>)>,
+    pub communication_servers: Vec<(NodeId, JoinHandle<()>)>,
+    pub text_servers: Vec<(NodeId, JoinHandle<()>)>,
+    pub media_servers: Vec<(NodeId, JoinHandle<()>)>,
+    /// Thread watching the drones when they are respawned on panic, the
+    /// drone threads are then in its hands instead of `drones`
+    pub drone_supervisor: Option<JoinHandle<Vec<NodePanic>>>,
+    /// Thread going through the `[[fault]]` entries, the drones it restores
+    /// run in its hands
+    pub fault_injector: Option<JoinHandle<Vec<NodePanic>>>,
+}
+
+/// Join handles of a running network, simulation controller included
+pub struct NetworkHandles {
+    pub controller: JoinHandle<()>,
+    pub nodes: NodeHandles,
+}
+
+/// A network running in the background, as returned by [`Network::start`]
+pub struct RunningNetwork {
+    pub handles: NetworkHandles,
+    commands: NodeCommands,
+    shutdown: Shutdown,
+    stats: StatsTable,
+    pausable: bool,
+    // Holds drone packet senders, dropped before joining
+    probes: Probes,
+    // Keeps the controller's GUI command channel open
+    _gui_command_send: Sender<GUICommands>,
+}
+
+impl Network {
+    /// Create every channel and node described by `network_config`, with
+    /// the default drone implementations
+    pub fn from_config(
+        network_config: &NetworkConfig,
+        options: &RunOptions,
+    ) -> Result<Self, NetworkInitError> {
+        Self::from_config_with_registry(network_config, options, &Registries::default())
+    }
+
+    /// Create every channel and node described by `network_config`, taking
+    /// the node implementations from `registries`
+    pub fn from_config_with_registry(
+        network_config: &NetworkConfig,
+        options: &RunOptions,
+        registries: &Registries,
+    ) -> Result<Self, NetworkInitError> {
+        let channels = build_channels(network_config, options.channel_capacity);
+        Self::from_channels(network_config, channels, options, registries)
+    }
+
+    /// Create every node described by `network_config` and wire it with
+    /// `channels`, as returned by [`build_channels`] for the same config
+    #[allow(clippy::too_many_lines)]
+    pub fn from_channels(
+        network_config: &NetworkConfig,
+        channels: Channels,
+        options: &RunOptions,
+        registries: &Registries,
+    ) -> Result<Self, NetworkInitError> {
+        let mut config = network_config.config.clone();
+        apply_pdr_range(&mut config, options.pdr_range, options.pdr_seed)?;
+        apply_pdr_override(&mut config, options.pdr_override)?;
+        let config = &config;
+
+        let Channels {
+            packet_send,
+            packet_recv,
+            command_send,
+            command_recv,
+            event_send,
+            event_recv,
+            comm_server_send,
+            comm_server_recv,
+            comm_server_event_send,
+            comm_server_event_recv,
+            text_server_send,
+            text_server_recv,
+            text_server_event_send,
+            text_server_event_recv,
+            media_server_send,
+            media_server_recv,
+            media_server_event_send,
+            media_server_event_recv,
+            server_kinds,
+            cclient_send,
+            cclient_recv,
+            cclient_event_send,
+            cclient_event_recv,
+            mclient_send,
+            mclient_recv,
+            mclient_event_send,
+            mclient_event_recv,
+            client_kinds,
+        } = channels;
+        let (packet_recv, relays) = if options.pausable {
+            add_relays(packet_recv, options.channel_capacity)
         } else {
-            // Media Client
-            let (mclient_command_send, mclient_command_recv) = unbounded::<MediaClientCommand>();
-            let (pkt_send, pkt_recv) = unbounded::<Packet>();
+            (packet_recv, Vec::new())
+        };
+        let links = Links::new(
+            network_config,
+            options.link_delay,
+            &packet_send,
+            options.channel_capacity,
+        );
+
+        // Nodes
+        let mut drones: Vec<(NodeId, Box<dyn Drone>)> = Vec::new();
+        let mut communication_servers = Vec::<(NodeId, Box<dyn Node>)>::new();
+        let mut text_servers = Vec::<(NodeId, Box<dyn Node>)>::new();
+        let mut media_servers = Vec::<(NodeId, Box<dyn Node>)>::new();
+        let mut chat_clients = Vec::<(NodeId, Box<dyn Node>)>::new();
+        let mut media_clients = Vec::<(NodeId, Box<dyn Node>)>::new();
+
+        // Hashmap of sender channel of drones
+        let mut drone_send = HashMap::<NodeId, (Sender<DroneCommand>, Sender<Packet>)>::new();
+
+        let mut timings = StartupTimings::default();
+        let started = Instant::now();
+        info!("[ {} ] Creating Drones", "Network Initializer".green());
+        // Choose which implementation every drone uses
+        let implementations = choose_implementations(network_config, options)?;
+
+        // Generate drones using factories
+        for drone in &config.drone {
+            let implementation = channel(&implementations, drone.id, "Implementation")?;
+
+            // Get right function
+            if let Some(factory) = registries.drones.get(implementation) {
+                let new_drone = factory(
+                    drone,
+                    &event_send,
+                    &command_recv,
+                    &links.senders_of(drone.id, &packet_send),
+                    &packet_recv,
+                )?;
+
+                drones.push((drone.id, new_drone));
+                trace!(
+                    "[ {} ] Created drone {} ({implementation})",
+                    "Network Initializer".green(),
+                    drone.id
+                );
+
+                let pkt_send = channel(&packet_send, drone.id, "Packet sender")?;
+                let cmd_send = channel(&command_send, drone.id, "Command sender")?;
+                drone_send.insert(drone.id, (cmd_send.clone(), pkt_send.clone()));
+            } else {
+                return Err(NetworkInitError::UnknownDroneImplementation {
+                    drone: drone.id,
+                    implementation: implementation.clone(),
+                });
+            }
+        }
 
-            packet_send.insert(client.id, pkt_send.clone());
-            packet_recv.insert(client.id, pkt_recv);
+        // Keep what's needed to rebuild the drones, on panic or on a
+        // scheduled restore
+        let restores = network_config
+            .faults
+            .iter()
+            .any(|fault| fault.restore_at.is_some());
+        let rebuilder = if options.respawn_drones || restores {
+            let mut factories = HashMap::new();
+            for drone in &config.drone {
+                let implementation = channel(&implementations, drone.id, "Implementation")?;
+                let factory = registries.drones.shared(implementation).ok_or_else(|| {
+                    NetworkInitError::UnknownDroneImplementation {
+                        drone: drone.id,
+                        implementation: implementation.clone(),
+                    }
+                })?;
+                factories.insert(drone.id, (drone.clone(), factory));
+            }
+            Some(DroneSupervisor::new(
+                factories,
+                event_send.clone(),
+                command_recv.clone(),
+                packet_send.clone(),
+                links.senders(),
+                packet_recv.clone(),
+            ))
+        } else {
+            None
+        };
+        timings.record("drone creation", started.elapsed());
+
+        // Client
+        let started = Instant::now();
+        info!(
+            "[ {} ] Creating ChatClient and MediaClient",
+            "Network Initializer".green()
+        );
+
+        // Clients and servers may only be wired to these
+        let drone_ids: HashSet<NodeId> = config.drone.iter().map(|d| d.id).collect();
+
+        // Generate clients
+        for client in &config.client {
+            // Get all neighbor Sender<Packet> channel
+            let cpkt_send = neighbor_senders(
+                client.id,
+                &client.connected_drone_ids,
+                &links.senders_of(client.id, &packet_send),
+                &drone_ids,
+            )?;
+
+            // Same kind the channels were built for
+            match *channel(&client_kinds, client.id, "Client kind")? {
+                ClientKind::Chat => {
+                    // ChatClient
+                    let factory = node_factory(
+                        &network_config.client_implementations,
+                        client.id,
+                        "chat client",
+                        DEFAULT_CHAT_CLIENT,
+                        |name| registries.clients.chat(name),
+                    )?;
+                    let cclient = factory(
+                        client.id,
+                        cclient_event_send.clone(),
+                        channel(&cclient_recv, client.id, "Command receiver")?.clone(),
+                        channel(&packet_recv, client.id, "Packet receiver")?.clone(),
+                        cpkt_send,
+                    );
+                    chat_clients.push((client.id, cclient));
+                }
+                ClientKind::Media => {
+                    // MediaClient
+                    let factory = node_factory(
+                        &network_config.client_implementations,
+                        client.id,
+                        "media client",
+                        DEFAULT_MEDIA_CLIENT,
+                        |name| registries.clients.media(name),
+                    )?;
+                    let mclient = factory(
+                        client.id,
+                        mclient_event_send.clone(),
+                        channel(&mclient_recv, client.id, "Command receiver")?.clone(),
+                        channel(&packet_recv, client.id, "Packet receiver")?.clone(),
+                        cpkt_send,
+                    );
+                    media_clients.push((client.id, mclient));
+                }
+            }
+        }
+
+        // Server
+        info!(
+            "[ {} ] Creating Communication and Content Servers",
+            "Network Initializer".green()
+        );
 
-            mclient_recv.insert(client.id, mclient_command_recv);
-            mclient_send.insert(client.id, (mclient_command_send, pkt_send));
+        for server in &config.server {
+            // Get all neighbor Sender<Packet> channel
+            let spkt_send = neighbor_senders(
+                server.id,
+                &server.connected_drone_ids,
+                &links.senders_of(server.id, &packet_send),
+                &drone_ids,
+            )?;
+
+            // Same kind the channels were built for
+            match *channel(&server_kinds, server.id, "Server kind")? {
+                ServerKind::Text => {
+                    // TextContentServer
+                    let factory = node_factory(
+                        &network_config.server_implementations,
+                        server.id,
+                        "text server",
+                        DEFAULT_CONTENT_SERVER,
+                        |name| registries.servers.text(name),
+                    )?;
+                    let text_server = factory(
+                        server.id,
+                        text_server_event_send.clone(),
+                        channel(&text_server_recv, server.id, "Command receiver")?.clone(),
+                        channel(&packet_recv, server.id, "Packet receiver")?.clone(),
+                        spkt_send,
+                    );
+                    text_servers.push((server.id, text_server));
+                }
+                ServerKind::Media => {
+                    // MediaContentServer
+                    let factory = node_factory(
+                        &network_config.server_implementations,
+                        server.id,
+                        "media server",
+                        DEFAULT_CONTENT_SERVER,
+                        |name| registries.servers.media(name),
+                    )?;
+                    let media_server = factory(
+                        server.id,
+                        media_server_event_send.clone(),
+                        channel(&media_server_recv, server.id, "Command receiver")?.clone(),
+                        channel(&packet_recv, server.id, "Packet receiver")?.clone(),
+                        spkt_send,
+                    );
+                    media_servers.push((server.id, media_server));
+                }
+                ServerKind::Communication => {
+                    // CommunicationServer
+                    let factory = node_factory(
+                        &network_config.server_implementations,
+                        server.id,
+                        "communication server",
+                        DEFAULT_COMMUNICATION_SERVER,
+                        |name| registries.servers.communication(name),
+                    )?;
+                    let comm_server = factory(
+                        server.id,
+                        comm_server_event_send.clone(),
+                        channel(&comm_server_recv, server.id, "Command receiver")?.clone(),
+                        channel(&packet_recv, server.id, "Packet receiver")?.clone(),
+                        spkt_send,
+                    );
+                    communication_servers.push((server.id, comm_server));
+                }
+            }
         }
 
-        count += 1;
+        timings.record("client and server creation", started.elapsed());
+
+        let client_implementations = client_implementations(network_config, &client_kinds);
+        let server_implementations = server_implementations(network_config, &server_kinds);
+        if log::log_enabled!(log::Level::Trace) {
+            log_nodes(&client_kinds, &client_implementations, "client");
+            log_nodes(&server_kinds, &server_implementations, "server");
+        }
+
+        let drone_commands = drone_send
+            .iter()
+            .map(|(id, (command_send, _))| (*id, command_send.clone()))
+            .collect();
+        let topology = build_topology(config);
+        let packets_sent = PacketCounter::default();
+        let mut shutdown = Shutdown::new(
+            drone_commands,
+            topology.as_map().clone(),
+            packets_sent.clone(),
+        );
+        if let Some(quiet) = options.drain {
+            let timeout = options.drain_timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT);
+            shutdown = shutdown.draining(quiet, timeout);
+        }
+
+        let live_topology = LiveTopology::new(topology.clone());
+        let fault_injector = (!network_config.faults.is_empty()).then(|| {
+            FaultInjector::new(
+                &network_config.faults,
+                &drone_send,
+                &topology,
+                &live_topology,
+                rebuilder.clone(),
+            )
+        });
+        let supervisor = rebuilder.filter(|_| options.respawn_drones);
+        let init_commands = init_commands(network_config, &packet_send);
+
+        let taps = Subscribers::default();
+        if let Some(drone_tap) = &options.event_taps.drone {
+            taps.add(drone_tap.clone());
+        }
+
+        let reloader = options.watch_config.then(|| {
+            let command_send = drone_send
+                .iter()
+                .map(|(id, (command_send, _))| (*id, command_send.clone()))
+                .collect();
+            ConfigReloader::new(
+                registries.drones.clone(),
+                options.clone(),
+                event_send.clone(),
+                packet_send.clone(),
+                links.senders(),
+                command_send,
+                topology.clone(),
+                live_topology.clone(),
+            )
+        });
+
+        Ok(Self {
+            config: config.clone(),
+            drones,
+            chat_clients,
+            media_clients,
+            communication_servers,
+            text_servers,
+            media_servers,
+            topology,
+            live_topology,
+            drone_send,
+            event_send,
+            event_recv,
+            cclient_send,
+            cclient_event_recv,
+            mclient_send,
+            mclient_event_recv,
+            comm_server_send,
+            comm_server_event_recv,
+            text_server_send,
+            text_server_event_recv,
+            media_server_send,
+            media_server_event_recv,
+            implementations,
+            client_implementations,
+            server_implementations,
+            client_kinds,
+            server_kinds,
+            stats: StatsTable::default(),
+            packets_sent,
+            timings,
+            supervisor,
+            reloader,
+            shutdown,
+            throughput_meter: options.throughput_meter,
+            metrics_port: options.metrics_port,
+            event_observer: options.event_observer.clone(),
+            relays,
+            links,
+            taps,
+            event_taps: options.event_taps.clone(),
+            on_ready: options.on_ready.clone(),
+            controller: registries.controller.clone(),
+            fault_injector,
+            init_commands,
+        })
+    }
+
+    /// Handle sending commands to single nodes once the network is running
+    pub fn commands(&self) -> NodeCommands {
+        NodeCommands::new(self)
     }
 
-    // Hashmap of sender channel of drones
-    let mut drones_hashmap = HashMap::<NodeId, (Sender<DroneCommand>, Sender<Packet>)>::new();
+    /// Handle that stops this network once it's running
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
 
-    // Create vector containing all the drones' function
-    let drone_factories = [
-        drone_factory::<rusty_drones::RustyDrone>(),
-        drone_factory::<LeDron_James::Drone>(),
-        drone_factory::<dr_ones::Drone>(),
-        drone_factory::<skylink::SkyLinkDrone>(),
-        drone_factory::<rustbusters_drone::RustBustersDrone>(),
-        //drone_factory::<rustbusters_drone::RustBustersDrone>(),
-        drone_factory::<rustbusters_drone::RustBustersDrone>(),
-        drone_factory::<rust_roveri::RustRoveri>(),
-        drone_factory::<rust_do_it::RustDoIt>(),
-        drone_factory::<wg_2024_rust::drone::RustDrone>(),
-        //drone_factory::<null_pointer_drone::MyDrone>(),
-        drone_factory::<lockheedrustin_drone::LockheedRustin>(),
-    ];
+    /// How many threads running the network takes: one per node, since
+    /// every node loop blocks on its own channels and none can share a
+    /// thread, plus the simulation controller, the GUI or the thread
+    /// draining its events when there's none, and the helpers the options
+    /// asked for. The thread running the controller or the GUI is counted
+    /// even when it's the caller's. The config watcher and respawned drones
+    /// come on top.
+    pub fn thread_count(&self) -> usize {
+        let nodes = self.drones.len()
+            + self.chat_clients.len()
+            + self.media_clients.len()
+            + self.communication_servers.len()
+            + self.text_servers.len()
+            + self.media_servers.len();
+        // Controller, GUI or GUI event drain, event tee, event sentinel and
+        // command tee
+        let mut helpers = 5 + self.relays.len() + self.links.len();
+        helpers += self.event_taps.server_taps();
+        helpers += usize::from(self.event_observer.is_some());
+        helpers += usize::from(self.throughput_meter);
+        helpers += usize::from(cfg!(feature = "metrics") && self.metrics_port.is_some());
+        helpers += usize::from(self.supervisor.is_some());
+        helpers += usize::from(self.fault_injector.is_some());
+        helpers += usize::from(self.on_ready.is_some());
+        nodes + helpers
+    }
+
+    /// Hold every packet on its way to a node until [`Network::resume`],
+    /// commands still go through. Only works when built with `pausable`,
+    /// and can be called before the network runs.
+    pub fn pause(&self) {
+        pause_relays(&self.shutdown, !self.relays.is_empty());
+    }
+
+    /// Deliver the packets held since [`Network::pause`]
+    pub fn resume(&self) {
+        self.shutdown.pause_switch().resume();
+    }
+
+    /// Run the whole network in the background, simulation controller
+    /// included, without a GUI: events meant for it are discarded. Returns
+    /// right away, the caller decides when to stop and join.
+    pub fn start(self) -> Result<RunningNetwork, NetworkInitError> {
+        let commands = self.commands();
+        let shutdown = self.shutdown_handle();
+        let stats = self.stats.clone();
+        let pausable = !self.relays.is_empty();
+        let probes = Probes::new(&self, self.taps.clone());
+
+        // Nobody drives the controller, but the command channel must stay open
+        let (gui_command_send, gui_command_recv) = unbounded::<GUICommands>();
+        let (gui_event_send, gui_event_recv) = unbounded::<GUIEvents>();
+        // The thread ends with the controller
+        spawn_named("gui-drain".to_string(), move || for _ in gui_event_recv {})?;
+        send_topology(&gui_event_send, self.config.clone());
+
+        let handles = self.spawn(gui_event_send, gui_command_recv)?;
+        Ok(RunningNetwork {
+            handles,
+            commands,
+            shutdown,
+            stats,
+            pausable,
+            probes,
+            _gui_command_send: gui_command_send,
+        })
+    }
+
+    /// Create the simulation controller, from the factory of the registries
+    /// the network was built with, and run it, together with every node, on
+    /// its own thread
+    pub fn spawn(
+        self,
+        gui_event_send: Sender<GUIEvents>,
+        gui_command_recv: Receiver<GUICommands>,
+    ) -> Result<NetworkHandles, NetworkInitError> {
+        let (mut simulation_controller, nodes) =
+            self.spawn_nodes(gui_event_send, gui_command_recv)?;
+
+        // Run simulation controller on different tread
+        let controller = spawn_named("sim-controller".to_string(), move || {
+            simulation_controller.run();
+        })?;
+
+        Ok(NetworkHandles { controller, nodes })
+    }
 
-    info!("[ {} ] Creating Drones", "Network Initializer".green());
-    // Generate drones using factories
-    for (n, drone) in config.drone.iter().enumerate() {
-        // Get right function
-        if let Some(factory) = drone_factories.get(n) {
-            let new_drone = factory(
-                drone,
-                &event_send,
-                &command_recv,
-                &packet_send,
-                &packet_recv,
+    /// Run every node on its own thread and return the simulation controller
+    /// without running it, so the caller decides where it runs
+    pub fn spawn_nodes(
+        self,
+        gui_event_send: Sender<GUIEvents>,
+        gui_command_recv: Receiver<GUICommands>,
+    ) -> Result<(Box<dyn Controller>, NodeHandles), NetworkInitError> {
+        // Taken before the command senders go to the controller
+        let commands = self.commands();
+        let init_commands = self.init_commands;
+        // Count drone events on their way to the controller. The controller
+        // keeps the drone-side sender, so drones it adds are counted too.
+        let (controller_event_send, controller_event_recv) = unbounded::<DroneEvent>();
+        let (event_recv, stats) = (self.event_recv, self.stats);
+        let packets_sent = self.packets_sent.clone();
+        if let Some(port) = self.metrics_port {
+            #[cfg(feature = "metrics")]
+            crate::metrics::serve(port, &stats, &self.shutdown)?;
+            #[cfg(not(feature = "metrics"))]
+            warn!(
+                "[ {} ] Built without the metrics feature, not serving metrics on port {port}",
+                "Network Initializer".yellow()
             );
+        }
+        // The observer gets its own copy of the stream, it ends with the tee
+        let observer_send = match self.event_observer {
+            Some(observer) => {
+                let (observer_send, observer_recv) = unbounded::<DroneEvent>();
+                spawn_named("event-observer".to_string(), move || {
+                    observer.observe(&observer_recv);
+                })?;
+                Some(observer_send)
+            }
+            None => None,
+        };
+        // Tapped streams reach the controller and the sentinel through their
+        // tap thread
+        let EventTaps {
+            comm, text, media, ..
+        } = self.event_taps;
+        let comm_server_event_recv = tapped("comm-server", self.comm_server_event_recv, comm)?;
+        let text_server_event_recv = tapped("text-server", self.text_server_event_recv, text)?;
+        let media_server_event_recv = tapped("media-server", self.media_server_event_recv, media)?;
+
+        // Drains the client and server events once the controller is gone,
+        // the tee does the same for drone events
+        let (gone_send, gone_recv) = bounded::<()>(1);
+        let sentinel = EventSentinel {
+            chat_client: self.cclient_event_recv.clone(),
+            media_client: self.mclient_event_recv.clone(),
+            communication_server: comm_server_event_recv.clone(),
+            text_server: text_server_event_recv.clone(),
+            media_server: media_server_event_recv.clone(),
+        };
+        spawn_named("event-sentinel".to_string(), move || {
+            sentinel.watch(&gone_recv);
+        })?;
+
+        let taps = self.taps;
+        spawn_named("event-tee".to_string(), move || {
+            stats::tee(
+                &event_recv,
+                &controller_event_send,
+                &stats,
+                &packets_sent,
+                observer_send.as_ref(),
+                &taps,
+                &gone_send,
+            );
+            // The controller holds a drone event sender, so it's gone as well
+            let _ = gone_send.try_send(());
+        })?;
+
+        // A relay ends once every sender of its outer channel is gone
+        for (id, outer_recv, inner_send) in self.relays {
+            let switch = self.shutdown.pause_switch().clone();
+            spawn_named(format!("relay-{id}"), move || {
+                pause::relay(&outer_recv, &inner_send, &switch);
+            })?;
+        }
 
-            drones.push(new_drone);
+        self.links.spawn()?;
 
-            if let Some(pkt_send) = packet_send.get(&drone.id) {
-                if let Some(cmd_send) = command_send.get(&drone.id) {
-                    drones_hashmap.insert(drone.id, (cmd_send.clone(), pkt_send.clone()));
-                } else {
-                    panic!("Command sender not found for drone {}", drone.id);
-                }
-            } else {
-                panic!("Packet sender not found for drone {}", drone.id);
+        if self.throughput_meter {
+            let (packets_sent, shutdown) = (self.packets_sent, self.shutdown.clone());
+            spawn_named("throughput-meter".to_string(), move || {
+                stats::meter(&packets_sent, &shutdown);
+            })?;
+        }
+
+        // The controller's drone commands, from the GUI among others, reach
+        // the drones through the command tee, so the live topology follows
+        // them too
+        let mut controller_drone_send = HashMap::new();
+        let mut followed = Vec::new();
+        for (id, (command_send, packet_send)) in self.drone_send {
+            let (tee_send, tee_recv) = unbounded::<DroneCommand>();
+            controller_drone_send.insert(id, (tee_send, packet_send));
+            followed.push((id, tee_recv, command_send));
+        }
+        let live_topology = self.live_topology.clone();
+        spawn_named("command-tee".to_string(), move || {
+            live_topology.follow(&followed);
+        })?;
+
+        // Simulation Controller
+        info!(
+            "[ {} ] Creating Simulation Controller",
+            "Network Initializer".green()
+        );
+        let simulation_controller = self.controller.build(ControllerChannels {
+            drone_send: controller_drone_send,
+            drone_event_recv: controller_event_recv,
+            topology: self.topology.into_map(),
+            drone_event_send: self.event_send,
+            gui_event_send,
+            gui_command_recv,
+            cclient_send: self.cclient_send,
+            cclient_event_recv: self.cclient_event_recv,
+            mclient_send: self.mclient_send,
+            mclient_event_recv: self.mclient_event_recv,
+            comm_server_send: self.comm_server_send,
+            comm_server_event_recv,
+            text_server_send: self.text_server_send,
+            text_server_event_recv,
+            media_server_send: self.media_server_send,
+            media_server_event_recv,
+        });
+
+        // Every node thread signals before running its node
+        let node_count = self.drones.len()
+            + self.chat_clients.len()
+            + self.media_clients.len()
+            + self.communication_servers.len()
+            + self.text_servers.len()
+            + self.media_servers.len();
+        let ready_send = match self.on_ready {
+            Some(callback) => {
+                let (ready_send, ready_recv) = unbounded::<()>();
+                ready::watch(node_count, ready_recv, callback)?;
+                Some(ready_send)
+            }
+            None => None,
+        };
+        // Every node thread waits here until all of them exist, this thread
+        // included. Threads are spawned in config order, drones first. If a
+        // spawn fails, the threads already spawned wait forever: an error
+        // there means the process is out of threads anyway.
+        let start = Arc::new(Barrier::new(node_count + 1));
+
+        // Run all members on different thread
+
+        let mut drones = Vec::new();
+        // Run drones on different threads
+        for (id, mut drone) in self.drones {
+            let (start, ready_send) = (Arc::clone(&start), ready_send.clone());
+            let handle = spawn_named(format!("drone-{id}"), move || {
+                start.wait();
+                ready::announce(ready_send.as_ref());
+                drone.run();
+            })?;
+            drones.push((id, handle));
+        }
+
+        let mut chat_clients = Vec::new();
+        // Run chat clients on different threads
+        for (id, mut client) in self.chat_clients {
+            let (start, ready_send) = (Arc::clone(&start), ready_send.clone());
+            let handle = spawn_named(format!("chat-{id}"), move || {
+                start.wait();
+                ready::announce(ready_send.as_ref());
+                client.run();
+            })?;
+            chat_clients.push((id, handle));
+        }
+
+        let mut media_clients = Vec::new();
+        // Run media client on different threads
+        for (id, mut mclient) in self.media_clients {
+            let (start, ready_send) = (Arc::clone(&start), ready_send.clone());
+            let handle = spawn_named(format!("media-{id}"), move || {
+                start.wait();
+                ready::announce(ready_send.as_ref());
+                mclient.run();
+            })?;
+            media_clients.push((id, handle));
+        }
+
+        let mut communication_servers = Vec::new();
+        // Run Servers
+        for (id, mut server) in self.communication_servers {
+            let (start, ready_send) = (Arc::clone(&start), ready_send.clone());
+            let handle = spawn_named(format!("comm-server-{id}"), move || {
+                start.wait();
+                ready::announce(ready_send.as_ref());
+                server.run();
+            })?;
+            communication_servers.push((id, handle));
+        }
+
+        let mut text_servers = Vec::new();
+        // Run Servers
+        for (id, mut server) in self.text_servers {
+            let (start, ready_send) = (Arc::clone(&start), ready_send.clone());
+            let handle = spawn_named(format!("text-server-{id}"), move || {
+                start.wait();
+                ready::announce(ready_send.as_ref());
+                server.run();
+            })?;
+            text_servers.push((id, handle));
+        }
+
+        let mut media_servers = Vec::new();
+        // Run Servers
+        for (id, mut server) in self.media_servers {
+            let (start, ready_send) = (Arc::clone(&start), ready_send.clone());
+            let handle = spawn_named(format!("media-server-{id}"), move || {
+                start.wait();
+                ready::announce(ready_send.as_ref());
+                server.run();
+            })?;
+            media_servers.push((id, handle));
+        }
+
+        start.wait();
+        for (drone, command) in init_commands {
+            if let Err(err) = commands.send_drone_command(drone, command) {
+                warn!(
+                    "[ {} ] Unable to send an init command to [ Drone {drone} ]: {err}",
+                    "Network Initializer".yellow()
+                );
+            }
+        }
+
+        // The schedule starts once every node runs
+        let fault_injector = match self.fault_injector {
+            Some(injector) => {
+                let shutdown = self.shutdown.clone();
+                Some(spawn_named("fault-injector".to_string(), move || {
+                    injector.run(&shutdown)
+                })?)
+            }
+            None => None,
+        };
+
+        // The supervisor takes over the drone threads
+        let drone_supervisor = match self.supervisor {
+            Some(supervisor) => {
+                let watched = std::mem::take(&mut drones);
+                let shutdown = self.shutdown;
+                Some(spawn_named("drone-supervisor".to_string(), move || {
+                    supervisor.supervise(watched, &shutdown)
+                })?)
+            }
+            None => None,
+        };
+
+        let nodes = NodeHandles {
+            drones,
+            chat_clients,
+            media_clients,
+            communication_servers,
+            text_servers,
+            media_servers,
+            drone_supervisor,
+            fault_injector,
+        };
+
+        Ok((simulation_controller, nodes))
+    }
+}
+
+/// The `init_commands` entries of every drone in config order, as drone
+/// commands. An `AddSender` of a node with no packet sender is left out.
+fn init_commands(
+    network_config: &NetworkConfig,
+    packet_send: &HashMap<NodeId, Sender<Packet>>,
+) -> Vec<(NodeId, DroneCommand)> {
+    let mut resolved = Vec::new();
+    for drone in &network_config.config.drone {
+        let Some(commands) = network_config.init_commands.get(&drone.id) else {
+            continue;
+        };
+        for command in commands {
+            let command = match *command {
+                InitCommand::SetPdr { pdr } => DroneCommand::SetPacketDropRate(pdr),
+                InitCommand::RemoveSender { node } => DroneCommand::RemoveSender(node),
+                InitCommand::AddSender { node } => match packet_send.get(&node) {
+                    Some(node_send) => DroneCommand::AddSender(node, node_send.clone()),
+                    None => continue,
+                },
+                InitCommand::Crash => DroneCommand::Crash,
+            };
+            resolved.push((drone.id, command));
+        }
+    }
+    resolved
+}
+
+/// Receiver of the events of `event_recv` once `tap_send` got a copy, fed by
+/// the thread `event-tap-{name}`. Without a tap, `event_recv` itself.
+fn tapped<T: Clone + Send + 'static>(
+    name: &str,
+    event_recv: Receiver<T>,
+    tap_send: Option<Sender<T>>,
+) -> Result<Receiver<T>, NetworkInitError> {
+    let Some(tap_send) = tap_send else {
+        return Ok(event_recv);
+    };
+    let (event_send, tapped_recv) = unbounded();
+    spawn_named(format!("event-tap-{name}"), move || {
+        stats::tap(&event_recv, &tap_send, &event_send);
+    })?;
+    Ok(tapped_recv)
+}
+
+/// Wait up to `timeout` for `unfinished` to report no running thread. Past
+/// it, every thread still running is logged and the network is told to stop,
+/// then the threads get `JOIN_GRACE` more. Returns the ones still running.
+fn wait_for(
+    unfinished: impl Fn() -> Vec<String>,
+    timeout: Duration,
+    shutdown: &Shutdown,
+) -> Vec<String> {
+    let wait = |timeout: Duration| {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let running = unfinished();
+            if running.is_empty() || Instant::now() >= deadline {
+                return running;
+            }
+            thread::sleep(JOIN_POLL_INTERVAL);
+        }
+    };
+
+    let running = wait(timeout);
+    if running.is_empty() {
+        return running;
+    }
+    for thread in &running {
+        warn!(
+            "[ {} ] {thread} did not exit",
+            "Network Initializer".yellow()
+        );
+    }
+    shutdown.trigger();
+    wait(JOIN_GRACE)
+}
+
+impl NodeHandles {
+    /// Labels of the node threads that are still running, e.g. "drone 4"
+    pub fn unfinished(&self) -> Vec<String> {
+        let groups = [
+            ("drone", &self.drones),
+            ("chat client", &self.chat_clients),
+            ("media client", &self.media_clients),
+            ("communication server", &self.communication_servers),
+            ("text server", &self.text_servers),
+            ("media server", &self.media_servers),
+        ];
+
+        let mut running: Vec<String> = groups
+            .into_iter()
+            .flat_map(|(kind, handles)| {
+                handles
+                    .iter()
+                    .filter(|(_, handle)| !handle.is_finished())
+                    .map(move |(id, _)| format!("{kind} {id}"))
+            })
+            .collect();
+        if let Some(supervisor) = &self.drone_supervisor {
+            if !supervisor.is_finished() {
+                running.push("drone supervisor".to_string());
+            }
+        }
+        if let Some(injector) = &self.fault_injector {
+            if !injector.is_finished() {
+                running.push("fault injector".to_string());
             }
+        }
+        running
+    }
+
+    /// Like [`NodeHandles::join`], giving up on threads still running after
+    /// `timeout` and a stop broadcast
+    pub fn join_timeout(
+        self,
+        timeout: Duration,
+        shutdown: &Shutdown,
+    ) -> Result<(), NetworkInitError> {
+        let running = wait_for(|| self.unfinished(), timeout, shutdown);
+        if running.is_empty() {
+            self.join()
         } else {
-            panic!("No factory defined for [ Drone {} ]", drone.id);
+            Err(NetworkInitError::JoinTimeout(running))
         }
     }
 
-    // Add to neighbor hashmap
-    let mut neighbor = HashMap::<NodeId, Vec<NodeId>>::new();
-    for drone in &config.drone {
-        neighbor.insert(drone.id, drone.connected_node_ids.clone());
+    /// Wait for every node thread to finish, even when some of them panicked
+    pub fn join(self) -> Result<(), NetworkInitError> {
+        let panics = self.join_all();
+        if panics.is_empty() {
+            Ok(())
+        } else {
+            Err(NetworkInitError::NodesPanicked(panics))
+        }
     }
 
-    // Client
-    info!(
-        "[ {} ] Creating ChatClient and MediaClient",
-        "Network Initializer".green()
-    );
+    /// Join all threads and collect the ones that panicked
+    fn join_all(self) -> Vec<NodePanic> {
+        let groups = [
+            ("drone", self.drones),
+            ("chat client", self.chat_clients),
+            ("media client", self.media_clients),
+            ("communication server", self.communication_servers),
+            ("text server", self.text_servers),
+            ("media server", self.media_servers),
+        ];
+
+        let mut panics = Vec::new();
+        for (kind, handles) in groups {
+            for (id, handle) in handles {
+                if let Err(payload) = handle.join() {
+                    panics.push(NodePanic::new(format!("{kind} {id}"), payload.as_ref()));
+                }
+            }
+        }
+
+        if let Some(supervisor) = self.drone_supervisor {
+            match supervisor.join() {
+                Ok(drone_panics) => panics.extend(drone_panics),
+                Err(payload) => panics.push(NodePanic::new(
+                    "drone supervisor".to_string(),
+                    payload.as_ref(),
+                )),
+            }
+        }
+        if let Some(injector) = self.fault_injector {
+            match injector.join() {
+                Ok(drone_panics) => panics.extend(drone_panics),
+                Err(payload) => panics.push(NodePanic::new(
+                    "fault injector".to_string(),
+                    payload.as_ref(),
+                )),
+            }
+        }
+        panics
+    }
+}
 
-    // Generate clients
-    count = 0;
-    for client in &config.client {
-        // Get all neighbor Sender<Packet> channel
-        let mut cpkt_send: HashMap<u8, Sender<Packet>> = HashMap::<u8, Sender<Packet>>::new();
-        for neighbor in &client.connected_drone_ids {
-            cpkt_send.insert(*neighbor, packet_send.get(neighbor).unwrap().clone());
+impl NetworkHandles {
+    /// Labels of the threads that are still running
+    pub fn unfinished(&self) -> Vec<String> {
+        let mut running = self.nodes.unfinished();
+        if !self.controller.is_finished() {
+            running.push("simulation controller".to_string());
         }
+        running
+    }
 
-        if count < half {
-            // ChatClient
-            let cclient = ChatClient::new(
-                client.id,
-                cclient_event_send.clone(),
-                cclient_recv.get(&client.id).unwrap().clone(),
-                packet_recv.get(&client.id).unwrap().clone(),
-                cpkt_send,
-            );
-            chat_clients.push(cclient);
+    /// Like [`NetworkHandles::join`], giving up on threads still running
+    /// after `timeout` and a stop broadcast
+    pub fn join_timeout(
+        self,
+        timeout: Duration,
+        shutdown: &Shutdown,
+    ) -> Result<(), NetworkInitError> {
+        let running = wait_for(|| self.unfinished(), timeout, shutdown);
+        if running.is_empty() {
+            self.join()
         } else {
-            // MediaClient
-            let mclient = MediaClient::new(
-                client.id,
-                mclient_event_send.clone(),
-                mclient_recv.get(&client.id).unwrap().clone(),
-                packet_recv.get(&client.id).unwrap().clone(),
-                cpkt_send,
-            );
-            media_clients.push(mclient);
+            Err(NetworkInitError::JoinTimeout(running))
+        }
+    }
+
+    /// Wait for every thread of the network to finish, even when some of
+    /// them panicked
+    pub fn join(self) -> Result<(), NetworkInitError> {
+        let mut panics = self.nodes.join_all();
+        if let Err(payload) = self.controller.join() {
+            panics.push(NodePanic::new(
+                "simulation controller".to_string(),
+                payload.as_ref(),
+            ));
+        }
+
+        if panics.is_empty() {
+            Ok(())
+        } else {
+            Err(NetworkInitError::NodesPanicked(panics))
         }
-        // Add client to neighbor hashmap
-        neighbor.insert(client.id, client.connected_drone_ids.clone());
+    }
+}
 
-        count += 1;
+impl RunningNetwork {
+    /// Handle sending commands to single nodes
+    pub fn commands(&self) -> &NodeCommands {
+        &self.commands
     }
 
-    // Server
+    /// Handle that stops the network, to keep beyond `self`
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Crash every drone, the clients and servers follow once their channels
+    /// disconnect
+    pub fn shutdown(&self) {
+        self.shutdown.trigger();
+    }
+
+    /// Wait at most `timeout` for the drones to send no packet for the quiet
+    /// period, `drain` in the options or else
+    /// [`crate::shutdown::DEFAULT_DRAIN_QUIET`], then shut down as
+    /// [`RunningNetwork::shutdown`] does. Returns whether they went quiet in
+    /// time. Nothing stops the clients from sending meanwhile, the messages
+    /// crate has no command for it.
+    pub fn shutdown_drain(&self, timeout: Duration) -> bool {
+        self.shutdown.drain(timeout)
+    }
+
+    /// Packets sent and dropped by every drone so far
+    pub fn stats(&self) -> HashMap<NodeId, PacketStats> {
+        stats::snapshot(&self.stats)
+    }
+
+    /// See [`Network::pause`]
+    pub fn pause(&self) {
+        pause_relays(&self.shutdown, self.pausable);
+    }
+
+    /// See [`Network::resume`]
+    pub fn resume(&self) {
+        self.shutdown.pause_switch().resume();
+    }
+
+    pub(crate) fn probes(&self) -> &Probes {
+        &self.probes
+    }
+
+    /// Wait for every thread of the network to finish, see
+    /// [`NetworkHandles::join`]
+    pub fn join_all(self) -> Result<(), NetworkInitError> {
+        let Self {
+            handles, probes, ..
+        } = self;
+        // The probe senders would keep the drones' packet channels open
+        drop(probes);
+        handles.join()
+    }
+
+    /// Like [`RunningNetwork::join_all`], giving up on threads still running
+    /// after `timeout` and a stop broadcast
+    pub fn join_timeout(self, timeout: Duration) -> Result<(), NetworkInitError> {
+        let Self {
+            handles,
+            probes,
+            shutdown,
+            ..
+        } = self;
+        drop(probes);
+        handles.join_timeout(timeout, &shutdown)
+    }
+}
+
+/// Build and run the network of the config files in `options`, with the
+/// default node implementations, until it's stopped
+pub fn run(options: RunOptions) -> Result<(), NetworkInitError> {
+    if options.config_paths.is_empty() {
+        run_with_registry(DEFAULT_CONFIG_PATH, &options, &Registries::default())
+    } else {
+        run_with_fragments(&options.config_paths, &options, &Registries::default())
+    }
+}
+
+/// Same as [`run`], with the node implementations taken from `registries`
+pub fn run_with_registry(
+    config_path: impl AsRef<Path>,
+    options: &RunOptions,
+    registries: &Registries,
+) -> Result<(), NetworkInitError> {
     info!(
-        "[ {} ] Creating Communication and Content Servers",
+        "[ {} ] Starting Network Initializer",
         "Network Initializer".green()
     );
+    // Open and read File
+    let mut timings = StartupTimings::default();
+    let config_path = config_path.as_ref();
+    let network_config = timings.time("config parse", || {
+        open_as(config_path, options.config_format, options.expand_env)
+    })?;
+    // The standard input can't be read again, there's nothing to watch
+    let watched = (config_path != Path::new(STDIN_PATH)).then_some(config_path);
+    run_network(network_config, options, registries, watched, timings)
+}
 
-    count = config.server.len();
-    for server in &config.server {
-        // Get all neighbor Sender<Packet> channel
-        let mut spkt_send = HashMap::<u8, Sender<Packet>>::new();
-        for neighbor in &server.connected_drone_ids {
-            spkt_send.insert(*neighbor, packet_send.get(neighbor).unwrap().clone());
-        }
+/// Same as [`run_with_registry`], with the config merged from the fragments
+/// at `config_paths` by [`load_configs`]. Only the config of a single file
+/// can be watched.
+pub fn run_with_fragments(
+    config_paths: &[impl AsRef<Path>],
+    options: &RunOptions,
+    registries: &Registries,
+) -> Result<(), NetworkInitError> {
+    if let [config_path] = config_paths {
+        return run_with_registry(config_path, options, registries);
+    }
 
-        if count > (third * 2) {
-            // TextContentServer
-            let text_server = ContentServer::new(
-                server.id,
-                packet_recv.get(&server.id).unwrap().clone(),
-                spkt_send,
-                text_server_event_send.clone(),
-                text_server_recv.get(&server.id).unwrap().clone(),
-                ServerType::Text,
-            );
-            text_servers.push(text_server);
-        } else if count > third {
-            // MediaContentServer
-            let media_server = ContentServer::new(
-                server.id,
-                packet_recv.get(&server.id).unwrap().clone(),
-                spkt_send,
-                media_server_event_send.clone(),
-                media_server_recv.get(&server.id).unwrap().clone(),
-                ServerType::Media,
-            );
-            media_servers.push(media_server);
-        } else {
-            // CommunicationServer
-            let comm_server = CommunicationServer::new(
-                server.id,
-                packet_recv.get(&server.id).unwrap().clone(),
-                spkt_send,
-                comm_server_event_send.clone(),
-                comm_server_recv.get(&server.id).unwrap().clone(),
+    info!(
+        "[ {} ] Starting Network Initializer with {} config fragments",
+        "Network Initializer".green(),
+        config_paths.len()
+    );
+    let mut timings = StartupTimings::default();
+    let network_config = timings.time("config parse", || {
+        load_configs(config_paths, options.config_format, options.expand_env)
+    })?;
+    run_network(network_config, options, registries, None, timings)
+}
+
+/// Same as [`run_with_registry`], with a config built in memory instead of
+/// read from a file. `watch_config` is ignored, there's no file to watch.
+pub fn run_with_config(
+    network_config: NetworkConfig,
+    options: &RunOptions,
+    registries: &Registries,
+) -> Result<(), NetworkInitError> {
+    info!(
+        "[ {} ] Starting Network Initializer",
+        "Network Initializer".green()
+    );
+    run_network(
+        network_config,
+        options,
+        registries,
+        None,
+        StartupTimings::default(),
+    )
+}
+
+/// Everything `run` does once the config is parsed, `config_path` is where
+/// it was read from and `timings` how long reading it took
+fn run_network(
+    mut network_config: NetworkConfig,
+    options: &RunOptions,
+    registries: &Registries,
+    config_path: Option<&Path>,
+    mut timings: StartupTimings,
+) -> Result<(), NetworkInitError> {
+    // Fail fast on a broken topology
+    let options = timings.time("validation", || {
+        prepare(&mut network_config, options, registries)
+    })?;
+    let options = &*options;
+
+    if let Some(path) = &options.dot_output {
+        info!(
+            "[ {} ] Writing topology to {}",
+            "Network Initializer".green(),
+            path.display()
+        );
+        return write_output(path, &dot::topology_to_dot(&network_config));
+    }
+
+    if options.dry_run {
+        return dry_run(network_config, options, registries);
+    }
+
+    let mut network = build_network(&network_config, options, registries, &mut timings)?;
+    let counts = node_counts(&network);
+    let config = network.config.clone();
+    let stats = network.stats.clone();
+    let coverage = (options.drone_assignment == DroneAssignment::Spread)
+        .then(|| coverage_report(&network.implementations));
+
+    // Ctrl-C crashes every drone and closes the GUI
+    let shutdown = network.shutdown_handle();
+    shutdown.install_ctrlc_handler();
+    if let Some(duration) = options.duration {
+        shutdown.trigger_after(duration)?;
+    }
+
+    // Started before the network, drones it adds must not miss the stop
+    let watcher = match (network.reloader.take(), config_path) {
+        (Some(reloader), Some(path)) => Some(reloader.watch(path.to_path_buf(), shutdown.clone())?),
+        (Some(_), None) => {
+            warn!(
+                "[ {} ] The config wasn't read from a single file, it can't be watched",
+                "Network Initializer".yellow()
             );
-            communication_servers.push(comm_server);
+            None
         }
-        // Add server to neighbor hashmap
-        neighbor.insert(server.id, server.connected_drone_ids.clone());
-
-        count -= 1;
+        (None, _) => None,
+    };
+
+    let drone_ids: Vec<NodeId> = config.drone.iter().map(|d| d.id).collect();
+    let stats_csv = options.stats_csv.as_deref();
+    if options.repl {
+        let pausable = !network.relays.is_empty();
+        repl::spawn(network.commands(), shutdown.clone(), pausable)?;
     }
 
     // GUI channels
     let (gui_command_send, gui_command_recv) = unbounded::<GUICommands>();
     let (gui_event_send, gui_event_recv) = unbounded::<GUIEvents>();
-    let gui_send = gui_event_send.clone();
+    let gui = (!options.headless)
+        .then(|| create_gui(gui_command_send, gui_event_recv))
+        .flatten();
+
+    // A GUI that can't be built leaves the network headless
+    let Some(gui) = gui else {
+        let result = run_headless(network, config, options.join_timeout, timings, &counts);
+        report_stats(&stats, &drone_ids, stats_csv, join_watcher(watcher, result))?;
+        log_coverage(coverage.as_deref());
+        return Ok(());
+    };
+
+    if options.no_topology_event {
+        info!(
+            "[ {} ] Not sending the topology to the GUI",
+            "Network Initializer".green()
+        );
+    } else {
+        for mismatch in gui_kind_mismatches(&network) {
+            warn!("[ {} ] {mismatch}", "Network Initializer".yellow());
+        }
+        send_topology(&gui_event_send, config);
+    }
 
-    // Simulation Controller
-    info!(
-        "[ {} ] Creating Simulation Controller",
-        "Network Initializer".green()
-    );
-    let mut simulation_controller = SimulationController::new(
-        drones_hashmap,
-        event_recv,
-        neighbor,
-        event_send,
-        gui_event_send,
-        gui_command_recv,
-        cclient_send,
-        cclient_event_recv,
-        mclient_send,
-        mclient_event_recv,
-        comm_server_send,
-        comm_server_event_recv,
-        text_server_send,
-        text_server_event_recv,
-        media_server_send,
-        media_server_event_recv,
-    );
+    let handles = timings.time("controller creation and node spawn", || {
+        network.spawn(gui_event_send, gui_command_recv)
+    })?;
+    // Run GUI on main thread
+    let gui_started = Instant::now();
+    let launched = launch_gui_with(gui, &shutdown, move || {
+        timings.record("GUI until first frame", gui_started.elapsed());
+        log_startup(&timings, &counts);
+    });
+    // The nodes already run, they go on without a window until stopped
+    if let Err(err) = launched {
+        warn!(
+            "[ {} ] {err}, the network runs headless until Ctrl-C",
+            "Network Initializer".yellow()
+        );
+        shutdown.wait();
+    }
+    // Closing the window stops the network, otherwise nothing would wake the
+    // nodes blocked on their receivers and the join below would never return
+    shutdown.trigger();
+
+    let result = match options.join_timeout {
+        Some(timeout) => handles.join_timeout(timeout, &shutdown),
+        None => handles.join(),
+    };
+    report_stats(&stats, &drone_ids, stats_csv, join_watcher(watcher, result))?;
+    log_coverage(coverage.as_deref());
+    Ok(())
+}
+
+/// Wait for the config watcher, once the network it watches is over with
+/// `result`, adding the panics of the drones it spawned
+fn join_watcher(
+    watcher: Option<JoinHandle<Vec<NodePanic>>>,
+    result: Result<(), NetworkInitError>,
+) -> Result<(), NetworkInitError> {
+    let Some(watcher) = watcher else {
+        return result;
+    };
+    let mut panics = watcher.join().unwrap_or_else(|payload| {
+        vec![NodePanic::new(
+            "config watcher".to_string(),
+            payload.as_ref(),
+        )]
+    });
 
-    // Run all members on different thread
+    match result {
+        Ok(()) if panics.is_empty() => Ok(()),
+        Ok(()) => Err(NetworkInitError::NodesPanicked(panics)),
+        Err(NetworkInitError::NodesPanicked(mut network_panics)) => {
+            network_panics.append(&mut panics);
+            Err(NetworkInitError::NodesPanicked(network_panics))
+        }
+        Err(err) => Err(err),
+    }
+}
 
-    // Run simulation controller on different tread
-    let controller_handle = thread::spawn(move || {
-        simulation_controller.run();
+/// Build a validated config into a network ready to spawn, with the checks
+/// and outputs that need the built nodes
+fn build_network(
+    network_config: &NetworkConfig,
+    options: &RunOptions,
+    registries: &Registries,
+    timings: &mut StartupTimings,
+) -> Result<Network, NetworkInitError> {
+    // Wire every channel, then build the nodes on top of them
+    let channels = timings.time("channel creation", || {
+        build_channels(network_config, options.channel_capacity)
     });
+    let network = Network::from_channels(network_config, channels, options, registries)?;
+    timings.extend(&network.timings);
+
+    check_connectivity(&network.topology, options.strict_connectivity)?;
+    if let Some(max) = options.max_threads {
+        let needed = network.thread_count();
+        if needed > max {
+            return Err(NetworkInitError::TooManyThreads { needed, max });
+        }
+    }
 
-    let mut drone_handles = Vec::new();
-    // Run drones on different threads
-    for mut drone in drones {
-        let handle = thread::spawn(move || {
-            drone.run();
-        });
-        drone_handles.push(handle);
+    if let Some(path) = &options.summary_json {
+        info!(
+            "[ {} ] Writing network summary to {}",
+            "Network Initializer".green(),
+            path.display()
+        );
+        write_output(path, &format!("{:#}\n", plan::summary_json(&network)))?;
     }
 
-    let mut cclient_handles = Vec::new();
-    // Run chat clients on different threads
-    for mut client in chat_clients {
-        let handle = thread::spawn(move || {
-            client.run();
-        });
-        cclient_handles.push(handle);
+    if options.print_topology {
+        print!("{}", plan::topology_table(&network));
     }
 
-    let mut mclient_handles = Vec::new();
-    // Run media client on different threads
-    for mut mclient in media_clients {
-        let handle = thread::spawn(move || {
-            mclient.run();
-        });
-        mclient_handles.push(handle);
+    if let Some(path) = &options.snapshot_out {
+        info!(
+            "[ {} ] Writing network snapshot to {}",
+            "Network Initializer".green(),
+            path.display()
+        );
+        let snapshot = snapshot::snapshot(&network);
+        write_output(
+            path,
+            &snapshot::serialize(&snapshot, ConfigFormat::from_path(path)),
+        )?;
     }
 
-    let mut comm_server_handles = Vec::new();
-    // Run Servers
-    for mut server in communication_servers {
-        let handle = thread::spawn(move || {
-            server.run();
-        });
-        comm_server_handles.push(handle);
+    if let Some(path) = &options.assignment_out {
+        info!(
+            "[ {} ] Writing drone assignment to {}",
+            "Network Initializer".green(),
+            path.display()
+        );
+        let assignment = Assignment::from_network(&network);
+        write_output(path, &assignment.serialize(ConfigFormat::from_path(path)))?;
     }
 
-    let mut text_server_handles = Vec::new();
-    // Run Servers
-    for mut server in text_servers {
-        let handle = thread::spawn(move || {
-            server.run();
-        });
-        text_server_handles.push(handle);
+    Ok(network)
+}
+
+/// Leave out, draw and override what `options` ask for in `network_config`,
+/// then run every startup check. Every entry point goes through it before
+/// building the network, with the options it returns: with `assignment_in`,
+/// every drone gets its implementation and pdr from the table and the
+/// options drawing them are cleared.
+fn prepare<'a>(
+    network_config: &mut NetworkConfig,
+    options: &'a RunOptions,
+    registries: &Registries,
+) -> Result<Cow<'a, RunOptions>, NetworkInitError> {
+    omit_nodes(network_config, options);
+    apply_server_kinds(network_config, options.server_kinds.as_deref())?;
+    apply_server_mix(network_config, options.server_mix, options.server_mix_seed)?;
+    apply_server_remainder(network_config, options.server_remainder);
+    if let Some(path) = &options.impl_map {
+        ImplementationMap::open(path)?.apply(network_config, &registries.drones)?;
     }
+    // Every drone gets an explicit choice, nothing is left to the RNG
+    let options = match &options.assignment_in {
+        Some(path) => {
+            Assignment::open(path)?.apply(network_config)?;
+            Cow::Owned(RunOptions {
+                pdr_range: None,
+                drone_implementation: None,
+                ..options.clone()
+            })
+        }
+        None => Cow::Borrowed(options),
+    };
+    validation::validate(&network_config.config)?;
+    validation::validate_id_space(&network_config.config, options.id_policy)?;
+    validation::validate_faults(network_config)?;
+    validation::validate_init_commands(network_config)?;
+    check_drone_implementation(&options, &registries.drones)?;
+    Ok(options)
+}
 
-    let mut media_server_handles = Vec::new();
-    // Run Servers
-    for mut server in media_servers {
-        let handle = thread::spawn(move || {
-            server.run();
+/// Run the config of `options` `runs` times in a row, headless, and log
+/// the mean and standard deviation of the packets the drones sent, dropped
+/// and forwarded. Returns the totals of every run.
+///
+/// Run `n` gets seed `s + n` for its draws, `s` being `pdr_seed` or a random
+/// one, so a series is replayed with the same `pdr_seed`. A seeded drone
+/// assignment is seeded the same way, any other one is kept as is. Each run must end
+/// by itself, `duration` is required. Ctrl-C stops the current run and the
+/// series. `headless`, `repl` and `watch_config` are ignored, and
+/// `stats_csv` is left unwritten.
+pub fn run_repeated(
+    options: RunOptions,
+    registries: &Registries,
+    runs: usize,
+) -> Result<Vec<PacketStats>, NetworkInitError> {
+    let Some(duration) = options.duration else {
+        return Err(NetworkInitError::RepeatWithoutDuration);
+    };
+    let network_config = load_options_config(&options)?;
+    let first_seed = options.pdr_seed.unwrap_or_else(rand::random);
+    let slot = ShutdownSlot::default();
+    slot.install_ctrlc_handler();
+
+    let mut totals = Vec::with_capacity(runs);
+    for run in 0..runs {
+        if slot.is_interrupted() {
+            break;
+        }
+        let seed = first_seed.wrapping_add(run as u64);
+        info!(
+            "[ {} ] Run {} of {runs}, seed {seed}",
+            "Network Initializer".green(),
+            run + 1
+        );
+        let mut run_options = options.clone();
+        run_options.reseed(seed);
+        run_options.repl = false;
+        run_options.watch_config = false;
+
+        let mut network_config = network_config.clone();
+        let run_options = prepare(&mut network_config, &run_options, registries)?;
+        let mut timings = StartupTimings::default();
+        let network = build_network(&network_config, &run_options, registries, &mut timings)?;
+        let counts = node_counts(&network);
+        let config = network.config.clone();
+        let stats = network.stats.clone();
+        let shutdown = network.shutdown_handle();
+        slot.set(shutdown.clone());
+        shutdown.trigger_after(duration)?;
+
+        let result = run_headless(network, config, options.join_timeout, timings, &counts);
+        log_stats(&stats);
+        result?;
+        totals.push(stats::totals(&stats::snapshot(&stats)));
+    }
+
+    info!(
+        "[ {} ] Packets of all the drones over {} run(s):\n{}",
+        "Network Initializer".green(),
+        totals.len(),
+        stats::format_aggregate(&totals)
+    );
+    Ok(totals)
+}
+
+/// Run several independent networks side by side in this process, one for
+/// each entry of `networks` with the config files of its options.
+///
+/// Each network gets its own channels, nodes and simulation controller, so
+/// ids only have to be unique within their own config. They all run headless
+/// since eframe opens a single window per process, and `headless`,
+/// `dot_output` and `dry_run` are ignored. Ctrl-C stops every network.
+pub fn run_many(
+    networks: Vec<RunOptions>,
+    registries: &Registries,
+) -> Result<(), NetworkInitError> {
+    let mut built = Vec::with_capacity(networks.len());
+    for (n, options) in networks.iter().enumerate() {
+        info!("[ {} ] Building network {n}", "Network Initializer".green());
+        let mut network_config = load_options_config(options)?;
+        let options = prepare(&mut network_config, options, registries)?;
+        built.push((
+            n,
+            build_network(
+                &network_config,
+                &options,
+                registries,
+                &mut StartupTimings::default(),
+            )?,
+        ));
+    }
+
+    Shutdown::install_ctrlc_handler_for(
+        built
+            .iter()
+            .map(|(_, network)| network.shutdown_handle())
+            .collect(),
+    );
+
+    let mut handles = Vec::with_capacity(built.len());
+    for ((n, network), options) in built.into_iter().zip(&networks) {
+        if let Some(duration) = options.duration {
+            network.shutdown_handle().trigger_after(duration)?;
+        }
+        let join_timeout = options.join_timeout;
+        let config = network.config.clone();
+        let stats = network.stats.clone();
+        let counts = node_counts(&network);
+        let drone_ids: Vec<NodeId> = config.drone.iter().map(|d| d.id).collect();
+        let stats_csv = options.stats_csv.clone();
+        let handle = spawn_named(format!("network-{n}"), move || {
+            let result = run_headless(
+                network,
+                config,
+                join_timeout,
+                StartupTimings::default(),
+                &counts,
+            );
+            report_stats(&stats, &drone_ids, stats_csv.as_deref(), result)
+        })?;
+        handles.push((n, handle));
+    }
+
+    // Wait for all of them, report the first failure
+    let mut result = Ok(());
+    for (n, handle) in handles {
+        let outcome = handle.join().unwrap_or_else(|payload| {
+            let panic = NodePanic::new(format!("network {n}"), payload.as_ref());
+            Err(NetworkInitError::NodesPanicked(vec![panic]))
         });
-        media_server_handles.push(handle);
+        if let Err(err) = outcome {
+            error!("[ {} ] Network {n}: {err}", "Network Initializer".red());
+            if result.is_ok() {
+                result = Err(err);
+            }
+        }
     }
+    result
+}
+
+/// Queue the `Topology` event the GUI needs before anything else.
+///
+/// Call it as soon as the GUI channel exists: the event then comes first
+/// whether the network or the GUI is started next, and stays buffered until
+/// the GUI event loop drains it.
+fn send_topology(gui_send: &Sender<GUIEvents>, config: Config) {
+    let topology = GUIEvents::Topology(config.drone, config.client, config.server);
+    // The network doesn't need the GUI, it goes on without it
+    if gui_send.send(topology).is_err() {
+        warn!(
+            "[ {} ] The GUI is gone before getting the topology",
+            "Network Initializer".yellow()
+        );
+    }
+}
 
-    // GUI
+/// The GUI driven through `command_send` and `event_recv`, or `None` with a
+/// warning when building it panics
+fn create_gui(
+    command_send: Sender<GUICommands>,
+    event_recv: Receiver<GUIEvents>,
+) -> Option<SimCtrlGUI> {
     info!("[ {} ] Creating GUI", "Network Initializer".green());
-    let gui = SimCtrlGUI::new(gui_command_send, gui_event_recv);
-    gui_send
-        .send(GUIEvents::Topology(
-            config.drone,
-            config.client,
-            config.server,
-        ))
-        .unwrap();
+    match panic::catch_unwind(AssertUnwindSafe(|| {
+        SimCtrlGUI::new(command_send, event_recv)
+    })) {
+        Ok(gui) => Some(gui),
+        Err(payload) => {
+            let err = NetworkInitError::Gui(panic_message(payload.as_ref()));
+            warn!(
+                "[ {} ] {err}, running headless",
+                "Network Initializer".yellow()
+            );
+            None
+        }
+    }
+}
 
-    // Run GUI on main thread
+/// Clients and servers whose kind isn't the one their position in the config
+/// gives. `GUIEvents::Topology` comes from the `messages` crate and only
+/// carries the config entries, the GUI has to guess the kinds from the
+/// positions and shows these nodes as the wrong kind.
+fn gui_kind_mismatches(network: &Network) -> Vec<String> {
+    let clients = &network.config.client;
+    let servers = &network.config.server;
+    let mut mismatches = Vec::new();
+
+    for (position, client) in clients.iter().enumerate() {
+        let guessed = positional_client_kind(position, clients.len() / 2);
+        if let Some(kind) = network.client_kinds.get(&client.id) {
+            if *kind != guessed {
+                mismatches.push(format!(
+                    "Client {} is a {kind:?} client, the GUI shows it as a {guessed:?} client",
+                    client.id
+                ));
+            }
+        }
+    }
+    let split = positional_server_split(servers.len(), DEFAULT_SERVER_REMAINDER);
+    for (position, server) in servers.iter().enumerate() {
+        let guessed = positional_server_kind(position, &split);
+        if let Some(kind) = network.server_kinds.get(&server.id) {
+            if *kind != guessed {
+                mismatches.push(format!(
+                    "Server {} is a {kind:?} server, the GUI shows it as a {guessed:?} server",
+                    server.id
+                ));
+            }
+        }
+    }
+    mismatches
+}
+
+/// Run the GUI event loop on the current thread until the window is closed.
+/// Some platforms only allow it on the main thread. Fails when the window
+/// can't be opened.
+pub fn launch_gui(gui: SimCtrlGUI, shutdown: &Shutdown) -> Result<(), NetworkInitError> {
+    launch_gui_with(gui, shutdown, || {})
+}
+
+/// Same as [`launch_gui`], calling `on_ready` once the window is up, right
+/// before the first frame
+fn launch_gui_with(
+    gui: SimCtrlGUI,
+    shutdown: &Shutdown,
+    on_ready: impl FnOnce() + 'static,
+) -> Result<(), NetworkInitError> {
+    let shutdown = shutdown.clone();
     let options = eframe::NativeOptions::default();
-    let _ = eframe::run_native(
+    eframe::run_native(
         "Simulation Controller GUI",
         options,
-        Box::new(|_cc| Ok(Box::new(gui))),
+        Box::new(move |cc| {
+            shutdown.set_gui_context(cc.egui_ctx.clone());
+            on_ready();
+            Ok(Box::new(gui))
+        }),
+    )
+    .map_err(|err| NetworkInitError::Gui(err.to_string()))
+}
+
+/// How many nodes of each type `network` has, as a log line
+fn node_counts(network: &Network) -> String {
+    format!(
+        "{} drones, {} chat clients, {} media clients, {} communication servers, {} text servers, {} media servers",
+        network.drones.len(),
+        network.chat_clients.len(),
+        network.media_clients.len(),
+        network.communication_servers.len(),
+        network.text_servers.len(),
+        network.media_servers.len(),
+    )
+}
+
+/// Log how long each startup phase took and what was spawned
+fn log_startup(timings: &StartupTimings, counts: &str) {
+    info!(
+        "[ {} ] Spawned {counts}, startup took:\n{}",
+        "Network Initializer".green(),
+        timings.format_table()
     );
+}
 
-    // Join all threads
-    for handle in drone_handles {
-        handle.join().unwrap();
+/// Log which implementations ran, when spreading them was asked
+fn log_coverage(coverage: Option<&str>) {
+    if let Some(coverage) = coverage {
+        info!(
+            "[ {} ] Drone implementation coverage:\n{coverage}",
+            "Network Initializer".green()
+        );
     }
+}
 
-    for handle in cclient_handles {
-        handle.join().unwrap();
-    }
+/// Log the traffic handled by every drone
+fn log_stats(stats: &StatsTable) {
+    info!(
+        "[ {} ] Packets per drone:\n{}",
+        "Network Initializer".green(),
+        stats::format_table(&stats::snapshot(stats))
+    );
+}
 
-    for handle in mclient_handles {
-        handle.join().unwrap();
-    }
+/// Log the stats of a network that's over with `result`, and write them to
+/// `stats_csv` if given. Done whatever the result, a failed join is still
+/// reported first.
+fn report_stats(
+    stats: &StatsTable,
+    drones: &[NodeId],
+    stats_csv: Option<&Path>,
+    result: Result<(), NetworkInitError>,
+) -> Result<(), NetworkInitError> {
+    log_stats(stats);
+    let written = match stats_csv {
+        Some(path) => {
+            info!(
+                "[ {} ] Writing packet stats to {}",
+                "Network Initializer".green(),
+                path.display()
+            );
+            write_output(path, &stats::to_csv(drones, &stats::snapshot(stats)))
+        }
+        None => Ok(()),
+    };
+    result.and(written)
+}
+
+/// Run the network without the GUI: the simulation controller takes the
+/// current thread and every event meant for the GUI is discarded
+fn run_headless(
+    network: Network,
+    config: Config,
+    join_timeout: Option<Duration>,
+    mut timings: StartupTimings,
+    counts: &str,
+) -> Result<(), NetworkInitError> {
+    let shutdown = network.shutdown_handle();
+
+    // Nobody drives the controller, but the command channel must stay open
+    let (_gui_command_send, gui_command_recv) = unbounded::<GUICommands>();
+    let (gui_event_send, gui_event_recv) = unbounded::<GUIEvents>();
+
+    // Drain GUI events so they don't pile up, the thread ends with the controller
+    spawn_named("gui-drain".to_string(), move || for _ in gui_event_recv {})?;
 
-    for handle in comm_server_handles {
-        handle.join().unwrap();
+    send_topology(&gui_event_send, config);
+
+    let (mut simulation_controller, nodes) = timings
+        .time("controller creation and node spawn", || {
+            network.spawn_nodes(gui_event_send, gui_command_recv)
+        })?;
+    log_startup(&timings, counts);
+
+    info!(
+        "[ {} ] Running headless, Simulation Controller on the current thread",
+        "Network Initializer".green()
+    );
+    simulation_controller.run();
+
+    match join_timeout {
+        Some(timeout) => nodes.join_timeout(timeout, &shutdown),
+        None => nodes.join(),
     }
+}
 
-    for handle in text_server_handles {
-        handle.join().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{
+        ConfigBuilder,
+        ServerKind::{Communication, Media, Text},
+    };
+
+    #[test]
+    fn positional_server_split_gives_the_leftovers_to_the_remainder() {
+        // Text, media and communication counts for 1, 2, 4, 5 and 7 servers
+        let cases = [
+            (
+                Text,
+                [[1, 0, 0], [2, 0, 0], [2, 1, 1], [3, 1, 1], [3, 2, 2]],
+            ),
+            (
+                Media,
+                [[0, 1, 0], [0, 2, 0], [1, 2, 1], [1, 3, 1], [2, 3, 2]],
+            ),
+            (
+                Communication,
+                [[0, 0, 1], [0, 0, 2], [1, 1, 2], [1, 1, 3], [2, 2, 3]],
+            ),
+        ];
+        for (remainder, expected) in cases {
+            for (servers, [text, media, communication]) in [1, 2, 4, 5, 7].into_iter().zip(expected)
+            {
+                assert_eq!(
+                    positional_server_split(servers, remainder),
+                    [(Text, text), (Media, media), (Communication, communication)],
+                    "{servers} servers, {remainder:?} remainder"
+                );
+            }
+        }
     }
 
-    for handle in media_server_handles {
-        handle.join().unwrap();
+    /// Client that only records the kind it was built as
+    struct Recorded;
+
+    impl Node for Recorded {
+        fn run(&mut self) {}
     }
 
-    controller_handle.join().unwrap();
+    #[test]
+    fn clients_are_built_as_classify_clients_says() {
+        // Explicit kinds out of id and config order, and two positional
+        // clients, 12 in the first half of the config and 13 in the second
+        let network_config = ConfigBuilder::new()
+            .add_drone(1, 0.0, &[14, 12, 17, 11, 16, 13])
+            .add_client(14, &[1])
+            .add_client(12, &[1])
+            .add_client(17, &[1])
+            .add_client(11, &[1])
+            .add_client(16, &[1])
+            .add_client(13, &[1])
+            .client_kind(14, ClientKind::Media)
+            .client_kind(11, ClientKind::Chat)
+            .client_kind(17, ClientKind::Media)
+            .client_kind(16, ClientKind::Chat)
+            .build();
+
+        let built = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let mut registries = Registries::default();
+        let chat = Arc::clone(&built);
+        registries
+            .clients
+            .register_chat(DEFAULT_CHAT_CLIENT, move |id, _, _, _, _| {
+                chat.lock().unwrap().insert(id, ClientKind::Chat);
+                Box::new(Recorded)
+            });
+        let media = Arc::clone(&built);
+        registries
+            .clients
+            .register_media(DEFAULT_MEDIA_CLIENT, move |id, _, _, _, _| {
+                media.lock().unwrap().insert(id, ClientKind::Media);
+                Box::new(Recorded)
+            });
+
+        Network::from_config_with_registry(&network_config, &RunOptions::default(), &registries)
+            .expect("the network builds");
+        let expected = HashMap::from([
+            (11, ClientKind::Chat),
+            (12, ClientKind::Chat),
+            (13, ClientKind::Media),
+            (14, ClientKind::Media),
+            (16, ClientKind::Chat),
+            (17, ClientKind::Media),
+        ]);
+        assert_eq!(classify_clients(&network_config), expected);
+        assert_eq!(*built.lock().unwrap(), expected);
+    }
 }
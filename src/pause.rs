@@ -0,0 +1,62 @@
+//! Freezing the packet traffic of a running network.
+//!
+//! The node loops live in other crates and have no pause command, so the
+//! network is paused from the outside: with `pausable` set, every node gets
+//! its packets through a relay thread instead of straight from its
+//! neighbors. While paused the relays hold on, packets pile up in the
+//! channels the neighbors send to and no node gets any, commands still go
+//! through. Resuming releases the queued packets in order.
+
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+
+use wg_2024::packet::Packet;
+
+/// Cloneable switch shared by every relay of a network
+#[derive(Clone, Default)]
+pub struct PauseSwitch {
+    paused: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl PauseSwitch {
+    pub fn pause(&self) {
+        self.set(true);
+    }
+
+    pub fn resume(&self) {
+        self.set(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.0.lock().map(|paused| *paused).unwrap_or(false)
+    }
+
+    fn set(&self, paused: bool) {
+        let (lock, resumed) = &*self.paused;
+        if let Ok(mut current) = lock.lock() {
+            *current = paused;
+            resumed.notify_all();
+        }
+    }
+
+    /// Block while the switch is paused
+    fn wait(&self) {
+        let (lock, resumed) = &*self.paused;
+        if let Ok(paused) = lock.lock() {
+            // A poisoned lock can't be paused anymore, let the packets through
+            let _ = resumed.wait_while(paused, |paused| *paused);
+        }
+    }
+}
+
+/// Forward every packet of `from` to `to`, holding them while `switch` is
+/// paused. Returns once all the senders of `from` are gone, dropping `to` so
+/// the node sees its channel disconnect as it would without the relay.
+pub fn relay(from: &Receiver<Packet>, to: &Sender<Packet>, switch: &PauseSwitch) {
+    for packet in from {
+        switch.wait();
+        if to.send(packet).is_err() {
+            break;
+        }
+    }
+}
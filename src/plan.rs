@@ -0,0 +1,155 @@
+use colored::Colorize;
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+use wg_2024::network::NodeId;
+
+use crate::{
+    config::{self, NetworkConfig, NodeKind, ServerKind},
+    network_initializer::{classify_clients, classify_servers, FactoryAssignment, Network},
+};
+
+/// Describe what running `network_config` would build: the implementation
+/// of every drone, the kind of every client and server and the edge count
+pub fn summary(network_config: &NetworkConfig, implementations: &FactoryAssignment) -> String {
+    let config = &network_config.config;
+    let mut summary = String::new();
+
+    // `write!` on a String can't fail
+    let _ = writeln!(summary, "Drones ({}):", config.drone.len());
+    for drone in &config.drone {
+        let implementation = implementations
+            .get(&drone.id)
+            .map_or("none", String::as_str);
+        let _ = writeln!(
+            summary,
+            "  {:>3}  {implementation} (pdr {})",
+            drone.id, drone.pdr
+        );
+    }
+
+    let _ = writeln!(summary, "Clients ({}):", config.client.len());
+    let client_kinds = classify_clients(network_config);
+    for client in &config.client {
+        let kind = client_kinds[&client.id];
+        let _ = writeln!(summary, "  {:>3}  {kind}", client.id);
+    }
+
+    let server_kinds = classify_servers(network_config);
+    let split: Vec<String> = [
+        ServerKind::Text,
+        ServerKind::Media,
+        ServerKind::Communication,
+    ]
+    .into_iter()
+    .map(|kind| {
+        let count = server_kinds.values().filter(|k| **k == kind).count();
+        format!("{count} {kind}")
+    })
+    .collect();
+    let _ = writeln!(
+        summary,
+        "Servers ({}: {}):",
+        config.server.len(),
+        split.join(", ")
+    );
+    for server in &config.server {
+        let kind = server_kinds[&server.id];
+        let _ = writeln!(summary, "  {:>3}  {kind}", server.id);
+    }
+
+    let _ = writeln!(summary, "Edges: {}", config::edges(config).len());
+    summary
+}
+
+/// Describe the built network for external tools: every node with its kind,
+/// the implementation or subtype chosen for it and its neighbors, then the
+/// edge list. Taken from `network`, so it reflects the decisions actually made
+/// and pdr overrides.
+pub fn summary_json(network: &Network) -> Value {
+    let nodes: Vec<Value> = config::nodes(&network.config)
+        .map(|(kind, id, neighbors)| {
+            let mut node = json!({
+                "id": id,
+                "kind": kind.to_string(),
+                "neighbors": neighbors,
+            });
+            match kind {
+                NodeKind::Drone => {
+                    node["implementation"] = json!(network.implementations.get(&id));
+                    node["pdr"] = json!(network
+                        .config
+                        .drone
+                        .iter()
+                        .find(|drone| drone.id == id)
+                        .map(|drone| drone.pdr));
+                }
+                NodeKind::Client => {
+                    node["client_kind"] =
+                        json!(network.client_kinds.get(&id).map(ToString::to_string));
+                }
+                NodeKind::Server => {
+                    node["server_kind"] =
+                        json!(network.server_kinds.get(&id).map(ToString::to_string));
+                }
+            }
+            node
+        })
+        .collect();
+
+    let edges: Vec<[NodeId; 2]> = config::edges(&network.config)
+        .into_iter()
+        .map(|(a, b)| [a, b])
+        .collect();
+
+    json!({ "nodes": nodes, "edges": edges })
+}
+
+/// The built network as a table for the terminal: one row per node sorted by
+/// id, with its kind colored, its implementation or subtype and its sorted
+/// neighbors
+pub fn topology_table(network: &Network) -> String {
+    let mut rows: Vec<(NodeKind, NodeId, String, Vec<NodeId>)> = config::nodes(&network.config)
+        .map(|(kind, id, neighbors)| {
+            let mut neighbors = neighbors.to_vec();
+            neighbors.sort_unstable();
+            let detail = match kind {
+                NodeKind::Drone => network.implementations.get(&id).cloned(),
+                NodeKind::Client => network.client_kinds.get(&id).map(ToString::to_string),
+                NodeKind::Server => network.server_kinds.get(&id).map(ToString::to_string),
+            };
+            (kind, id, detail.unwrap_or_default(), neighbors)
+        })
+        .collect();
+    rows.sort_unstable_by_key(|(_, id, _, _)| *id);
+    let width = rows
+        .iter()
+        .map(|(_, _, detail, _)| detail.len())
+        .max()
+        .unwrap_or(0)
+        .max("IMPLEMENTATION".len());
+
+    let mut table = String::new();
+    // `write!` on a String can't fail
+    let _ = writeln!(
+        table,
+        "{:>3}  {:<6}  {:<width$}  NEIGHBORS",
+        "ID", "KIND", "IMPLEMENTATION"
+    );
+    for (kind, id, detail, neighbors) in rows {
+        // Padded before coloring, the escape codes would count in the width
+        let kind_cell = format!("{:<6}", kind.to_string());
+        let kind_cell = match kind {
+            NodeKind::Drone => kind_cell.cyan(),
+            NodeKind::Client => kind_cell.green(),
+            NodeKind::Server => kind_cell.magenta(),
+        };
+        let neighbors: Vec<String> = neighbors.iter().map(ToString::to_string).collect();
+        let _ = writeln!(
+            table,
+            "{id:>3}  {kind_cell}  {detail:<width$}  {}",
+            neighbors.join(", ")
+        );
+    }
+    table
+}
@@ -0,0 +1,261 @@
+//! Checking that packets actually get from every client to every server.
+//!
+//! The validation only looks at the config. A probe goes through the
+//! running drones instead: for each client, a flood request is handed to the
+//! first drone next to it as if the client had sent it. Every server the
+//! flood reaches answers with a flood response carrying its id in the path
+//! trace, and the drones report that response to the controller on its way
+//! back. The client ends up receiving a response to a flood it never
+//! started, which the protocol lets it discard.
+//!
+//! The health check probes each drone the same way, with a flood request
+//! coming from one of its neighbors: the drone proves it's responsive by
+//! reporting the flood it forwards, or the response it sends back. The
+//! client and server commands in `messages` have no no-op that gets an
+//! event back, so for them only whether their thread still runs is checked.
+
+use crossbeam_channel::Sender;
+use std::{
+    collections::{HashMap, HashSet},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use wg_2024::{
+    controller::DroneEvent,
+    network::{NodeId, SourceRoutingHeader},
+    packet::{FloodRequest, NodeType, Packet, PacketType},
+};
+
+use crate::{
+    config::NodeKind,
+    network_initializer::{Network, RunningNetwork},
+    stats::Subscribers,
+};
+
+/// Flood ids are counted down from here, far from the ones clients use
+const PROBE_FLOOD_ID: u64 = u64::MAX;
+/// Same for the health check, below the ids of the reachability probes
+const HEALTH_FLOOD_ID: u64 = PROBE_FLOOD_ID - 256;
+
+/// What a running network needs to be probed
+pub(crate) struct Probes {
+    // Every client with the packet sender of its first drone, if it has one
+    clients: Vec<(NodeId, Option<Sender<Packet>>)>,
+    servers: Vec<NodeId>,
+    // Every drone with its packet sender and a neighbor to pose as, if any
+    drones: Vec<(NodeId, Sender<Packet>, Option<(NodeId, NodeType)>)>,
+    taps: Subscribers,
+}
+
+impl Probes {
+    pub(crate) fn new(network: &Network, taps: Subscribers) -> Self {
+        let topology = &network.topology;
+        let clients = topology
+            .nodes_of_kind(NodeKind::Client)
+            .into_iter()
+            .map(|client| {
+                let first_drone = topology.neighbors(client).iter().find_map(|neighbor| {
+                    network
+                        .drone_send
+                        .get(neighbor)
+                        .map(|(_, packet_send)| packet_send.clone())
+                });
+                (client, first_drone)
+            })
+            .collect();
+
+        let drones = topology
+            .nodes_of_kind(NodeKind::Drone)
+            .into_iter()
+            .filter_map(|drone| {
+                let (_, packet_send) = network.drone_send.get(&drone)?;
+                let neighbor = topology.neighbors(drone).iter().find_map(|neighbor| {
+                    let node_type = match topology.kind(*neighbor)? {
+                        NodeKind::Drone => NodeType::Drone,
+                        NodeKind::Client => NodeType::Client,
+                        NodeKind::Server => NodeType::Server,
+                    };
+                    Some((*neighbor, node_type))
+                });
+                Some((drone, packet_send.clone(), neighbor))
+            })
+            .collect();
+
+        Self {
+            clients,
+            servers: topology.nodes_of_kind(NodeKind::Server),
+            drones,
+            taps,
+        }
+    }
+}
+
+fn flood_id(client: NodeId) -> u64 {
+    PROBE_FLOOD_ID - u64::from(client)
+}
+
+/// Flood request started by `client`, as a drone next to it receives it
+fn probe(client: NodeId) -> Packet {
+    flood_request(flood_id(client), (client, NodeType::Client))
+}
+
+/// Flood request `flood_id` coming from `initiator`, which started it
+fn flood_request(flood_id: u64, initiator: (NodeId, NodeType)) -> Packet {
+    Packet {
+        routing_header: SourceRoutingHeader {
+            hop_index: 0,
+            hops: Vec::new(),
+        },
+        session_id: flood_id,
+        pack_type: PacketType::FloodRequest(FloodRequest {
+            flood_id,
+            initiator_id: initiator.0,
+            path_trace: vec![initiator],
+        }),
+    }
+}
+
+/// Flood every client's neighborhood and wait at most `timeout` for each
+/// server to answer each client. Returns the `(client, server)` pairs that
+/// got no answer, sorted.
+///
+/// Lost answers can't be told apart from missing routes: a server that
+/// doesn't answer flood requests is reported unreachable, and so is every
+/// server from a client with no drone next to it.
+pub fn verify_reachability(
+    network: &RunningNetwork,
+    timeout: Duration,
+) -> Result<(), Vec<(NodeId, NodeId)>> {
+    let probes = network.probes();
+    let mut unreached: HashSet<(NodeId, NodeId)> = probes
+        .clients
+        .iter()
+        .flat_map(|(client, _)| probes.servers.iter().map(|server| (*client, *server)))
+        .collect();
+
+    // Subscribed first, so no answer comes before it
+    let event_recv = probes.taps.subscribe();
+    let mut probed = HashMap::new();
+    for (client, first_drone) in &probes.clients {
+        // A drone that's gone can't answer, its pairs stay unreached
+        if let Some(packet_send) = first_drone {
+            if packet_send.send(probe(*client)).is_ok() {
+                probed.insert(flood_id(*client), *client);
+            }
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    while !unreached.is_empty() {
+        let Ok(event) = event_recv.recv_deadline(deadline) else {
+            break;
+        };
+        // Drones that can't forward a response give it to the controller
+        let (DroneEvent::PacketSent(packet) | DroneEvent::ControllerShortcut(packet)) = event
+        else {
+            continue;
+        };
+        let PacketType::FloodResponse(response) = &packet.pack_type else {
+            continue;
+        };
+        let Some(client) = probed.get(&response.flood_id) else {
+            continue;
+        };
+        for (node, node_type) in &response.path_trace {
+            if *node_type == NodeType::Server {
+                unreached.remove(&(*client, *node));
+            }
+        }
+    }
+
+    if unreached.is_empty() {
+        return Ok(());
+    }
+    let mut unreached: Vec<_> = unreached.into_iter().collect();
+    unreached.sort_unstable();
+    Err(unreached)
+}
+
+/// State of a node found by [`health_check`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Alive,
+    /// The node's thread is gone, or the drone didn't answer in time
+    Unresponsive,
+}
+
+/// Whether the thread behind `handle` is still running
+fn running(handle: &JoinHandle<()>) -> Health {
+    if handle.is_finished() {
+        Health::Unresponsive
+    } else {
+        Health::Alive
+    }
+}
+
+/// Health of every node of `network`.
+///
+/// Clients and servers are alive while their thread runs. Drones must also
+/// answer a flood request within `timeout`, which catches a deadlocked
+/// drone. A drone without neighbors can't be sent a flood, its thread alone
+/// is checked, and the threads of drones watched by the supervisor aren't
+/// visible from here.
+pub fn health_check(network: &RunningNetwork, timeout: Duration) -> HashMap<NodeId, Health> {
+    let nodes = &network.handles.nodes;
+    let mut health: HashMap<NodeId, Health> = [
+        &nodes.chat_clients,
+        &nodes.media_clients,
+        &nodes.communication_servers,
+        &nodes.text_servers,
+        &nodes.media_servers,
+        &nodes.drones,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(id, handle)| (*id, running(handle)))
+    .collect();
+
+    let probes = network.probes();
+    let event_recv = probes.taps.subscribe();
+    let mut pending = HashMap::new();
+    for (drone, packet_send, neighbor) in &probes.drones {
+        // The thread is already known to be gone
+        if health.get(drone) == Some(&Health::Unresponsive) {
+            continue;
+        }
+        let Some(neighbor) = neighbor else {
+            health.entry(*drone).or_insert(Health::Alive);
+            continue;
+        };
+        let flood_id = HEALTH_FLOOD_ID - u64::from(*drone);
+        if packet_send.send(flood_request(flood_id, *neighbor)).is_ok() {
+            pending.insert(flood_id, *drone);
+        }
+        health.insert(*drone, Health::Unresponsive);
+    }
+
+    let deadline = Instant::now() + timeout;
+    while !pending.is_empty() {
+        let Ok(event) = event_recv.recv_deadline(deadline) else {
+            break;
+        };
+        let (DroneEvent::PacketSent(packet) | DroneEvent::ControllerShortcut(packet)) = event
+        else {
+            continue;
+        };
+        // Forwarded or answered, the drone has added itself to the trace
+        let (flood_id, path_trace) = match &packet.pack_type {
+            PacketType::FloodRequest(request) => (request.flood_id, &request.path_trace),
+            PacketType::FloodResponse(response) => (response.flood_id, &response.path_trace),
+            _ => continue,
+        };
+        if let Some(drone) = pending.get(&flood_id).copied() {
+            if path_trace.iter().any(|(node, _)| *node == drone) {
+                pending.remove(&flood_id);
+                health.insert(drone, Health::Alive);
+            }
+        }
+    }
+    health
+}
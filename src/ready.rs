@@ -0,0 +1,67 @@
+//! Telling tools when the whole network runs.
+//!
+//! Each node thread signals on a shared channel right before calling the
+//! node's `run`, and a watcher counts the signals down. The node
+//! implementations come from other crates and have no way to tell when
+//! their loop is actually entered: once every thread has signalled, the
+//! watcher waits a short grace period to let the last `run` calls reach
+//! their loop, then calls the callback. The GUI only knows the events of the
+//! `messages` crate, which has no `Ready` event, so only the callback is told.
+
+use colored::Colorize;
+use crossbeam_channel::{Receiver, Sender};
+use log::info;
+use std::{fmt, sync::Arc, thread, time::Duration};
+
+use crate::{error::NetworkInitError, network_initializer::spawn_named};
+
+/// Time left for the nodes to go from their signal into their loop
+pub const GRACE_PERIOD: Duration = Duration::from_millis(50);
+
+/// Called once every node of the network runs
+#[derive(Clone)]
+pub struct ReadyCallback(Arc<dyn Fn() + Send + Sync>);
+
+impl ReadyCallback {
+    pub fn new(callback: impl Fn() + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for ReadyCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReadyCallback")
+    }
+}
+
+/// Signal that the node running on this thread is about to start
+pub(crate) fn announce(ready_send: Option<&Sender<()>>) {
+    if let Some(ready_send) = ready_send {
+        // The watcher is gone when the network failed to start
+        let _ = ready_send.send(());
+    }
+}
+
+/// Wait on the thread `readiness` for `nodes` signals on `ready_recv`, then
+/// call `callback`. Once every sender is gone without all the signals, a
+/// node thread failed to start and the callback is never called.
+pub(crate) fn watch(
+    nodes: usize,
+    ready_recv: Receiver<()>,
+    callback: ReadyCallback,
+) -> Result<(), NetworkInitError> {
+    spawn_named("readiness".to_string(), move || {
+        for _ in 0..nodes {
+            if ready_recv.recv().is_err() {
+                return;
+            }
+        }
+        thread::sleep(GRACE_PERIOD);
+        info!(
+            "[ {} ] All {nodes} nodes are running",
+            "Network Initializer".green()
+        );
+        (callback.0)();
+    })?;
+    Ok(())
+}
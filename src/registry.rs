@@ -0,0 +1,643 @@
+use crossbeam_channel::{Receiver, Sender};
+use std::{
+    any,
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use wg_2024::{
+    config::Drone as ConfigDrone,
+    controller::{DroneCommand, DroneEvent},
+    drone::Drone,
+    network::NodeId,
+    packet::Packet,
+};
+
+use chat_client::ChatClient;
+use communication_server::servers::{
+    communication_server::CommunicationServer, content_server::ContentServer,
+};
+use media_client::media_client::MediaClient;
+use messages::{
+    client_commands::{ChatClientCommand, ChatClientEvent, MediaClientCommand, MediaClientEvent},
+    high_level_messages::ServerType,
+    server_commands::{
+        CommunicationServerCommand, CommunicationServerEvent, ContentServerCommand,
+        ContentServerEvent,
+    },
+};
+
+use crate::{
+    config::{ClientKind, ServerKind},
+    controller::ControllerFactory,
+    error::{panic_message, NetworkInitError},
+    network_initializer::{channel, warn_no_senders, warn_self_loop},
+};
+
+/// Builds a drone from its config entry and the channels of the network
+pub type DroneFactoryFn = dyn Fn(
+        &ConfigDrone,
+        &Sender<DroneEvent>,
+        &HashMap<NodeId, Receiver<DroneCommand>>,
+        &HashMap<NodeId, Sender<Packet>>,
+        &HashMap<NodeId, Receiver<Packet>>,
+    ) -> Result<Box<dyn Drone>, NetworkInitError>
+    + Send
+    + Sync;
+
+/// Factory for the drone implementation `T`
+pub fn drone_factory<T>() -> Box<DroneFactoryFn>
+where
+    T: Drone + 'static,
+{
+    Box::new(
+        |drone, event_send, command_recv_hashmap, packet_send_hashmap, packet_recv_hashmap| {
+            // Get drone's packet receiver channel
+            let packet_recv = channel(packet_recv_hashmap, drone.id, "Packet receiver")?;
+
+            // Create packet send hashmap
+            let mut packet_send = HashMap::<NodeId, Sender<Packet>>::new();
+            // Fill hashmap with only neighbor
+            for neighbor in &drone.connected_node_ids {
+                // Same guard as for clients and servers
+                if *neighbor == drone.id {
+                    warn_self_loop(drone.id);
+                    continue;
+                }
+                packet_send_hashmap
+                    .iter()
+                    .filter(|(node_id, _)| *node_id == neighbor)
+                    .for_each(|(node_id, channel)| {
+                        packet_send.insert(*node_id, channel.clone());
+                    });
+            }
+            // Otherwise a silent sink, neighbor ids that match no channel
+            // would go unnoticed
+            if packet_send.is_empty() {
+                warn_no_senders(drone.id, &drone.connected_node_ids);
+            }
+
+            // Get drone's command receiver channel
+            let command_recv = channel(command_recv_hashmap, drone.id, "Command receiver")?;
+
+            // Some implementations reject settings by panicking, e.g. a pdr
+            // of exactly 1.0
+            let new_drone = panic::catch_unwind(AssertUnwindSafe(|| {
+                T::new(
+                    drone.id,
+                    event_send.clone(),
+                    command_recv.clone(),
+                    packet_recv.clone(),
+                    packet_send,
+                    drone.pdr,
+                )
+            }))
+            .map_err(|payload| NetworkInitError::DroneConstruction {
+                id: drone.id,
+                implementation: any::type_name::<T>().to_string(),
+                payload: panic_message(payload.as_ref()),
+            })?;
+            Ok(Box::new(new_drone) as Box<dyn Drone>)
+        },
+    )
+}
+
+/// Implementation names in the order they're assigned to drones that don't
+/// request one explicitly, each with whether its cargo feature is enabled
+const DRONE_ORDER: [(&str, bool); 10] = [
+    ("rusty_drones", cfg!(feature = "drone-rusty")),
+    ("ledron_james", cfg!(feature = "drone-ledron-james")),
+    ("dr_ones", cfg!(feature = "drone-dr-ones")),
+    ("skylink", cfg!(feature = "drone-skylink")),
+    ("rustbusters", cfg!(feature = "drone-rustbusters")),
+    ("rustbusters", cfg!(feature = "drone-rustbusters")),
+    ("rust_roveri", cfg!(feature = "drone-rust-roveri")),
+    ("rust_do_it", cfg!(feature = "drone-rust-do-it")),
+    ("wg_2024_rust", cfg!(feature = "drone-wg-2024-rust")),
+    ("lockheedrustin", cfg!(feature = "drone-lockheedrustin")),
+];
+
+/// Implementation names in the order they're assigned to drones that don't
+/// request one explicitly: the n-th drone of the config gets the n-th entry.
+/// Only the implementations compiled in are listed.
+pub fn default_drone_order() -> Vec<&'static str> {
+    DRONE_ORDER
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Drone implementations available to the network, by name.
+///
+/// `DroneRegistry::default()` holds every vendor drone whose feature is enabled,
+/// register your own `impl Drone` to make it selectable from the config.
+#[derive(Clone)]
+pub struct DroneRegistry {
+    // Shared so a drone can be rebuilt from another thread after spawning
+    factories: HashMap<String, Arc<DroneFactoryFn>>,
+}
+
+impl DroneRegistry {
+    /// Registry without any implementation
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Make `T` available under `name`, replacing what was registered there
+    pub fn register<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: Drone + 'static,
+    {
+        self.factories
+            .insert(name.into(), Arc::from(drone_factory::<T>()));
+        self
+    }
+
+    /// Factory registered under `name`
+    pub fn get(&self, name: &str) -> Option<&DroneFactoryFn> {
+        self.factories.get(name).map(Arc::as_ref)
+    }
+
+    /// Factory registered under `name`, to keep beyond the registry's lifetime
+    pub fn shared(&self, name: &str) -> Option<Arc<DroneFactoryFn>> {
+        self.factories.get(name).cloned()
+    }
+
+    /// Whether something is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Names of every registered implementation, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for DroneRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        #[cfg(feature = "drone-rusty")]
+        registry.register::<rusty_drones::RustyDrone>("rusty_drones");
+        #[cfg(feature = "drone-ledron-james")]
+        registry.register::<LeDron_James::Drone>("ledron_james");
+        #[cfg(feature = "drone-dr-ones")]
+        registry.register::<dr_ones::Drone>("dr_ones");
+        #[cfg(feature = "drone-skylink")]
+        registry.register::<skylink::SkyLinkDrone>("skylink");
+        #[cfg(feature = "drone-rustbusters")]
+        registry.register::<rustbusters_drone::RustBustersDrone>("rustbusters");
+        #[cfg(feature = "drone-rust-roveri")]
+        registry.register::<rust_roveri::RustRoveri>("rust_roveri");
+        #[cfg(feature = "drone-rust-do-it")]
+        registry.register::<rust_do_it::RustDoIt>("rust_do_it");
+        #[cfg(feature = "drone-wg-2024-rust")]
+        registry.register::<wg_2024_rust::drone::RustDrone>("wg_2024_rust");
+        //registry.register::<null_pointer_drone::MyDrone>("null_pointer");
+        #[cfg(feature = "drone-lockheedrustin")]
+        registry.register::<lockheedrustin_drone::LockheedRustin>("lockheedrustin");
+        registry
+    }
+}
+
+/// A client or server, whatever its implementation, ready to run on its
+/// own thread
+pub trait Node: Send {
+    fn run(&mut self);
+}
+
+impl Node for ChatClient {
+    fn run(&mut self) {
+        ChatClient::run(self);
+    }
+}
+
+impl Node for MediaClient {
+    fn run(&mut self) {
+        MediaClient::run(self);
+    }
+}
+
+impl Node for ContentServer {
+    fn run(&mut self) {
+        ContentServer::run(self);
+    }
+}
+
+impl Node for CommunicationServer {
+    fn run(&mut self) {
+        CommunicationServer::run(self);
+    }
+}
+
+/// Builds a client or server speaking commands `C` and events `E` from its
+/// id, its event sender, its command receiver, its packet receiver and the
+/// packet senders of its neighbors
+pub type NodeFactoryFn<C, E> = dyn Fn(
+        NodeId,
+        Sender<E>,
+        Receiver<C>,
+        Receiver<Packet>,
+        HashMap<NodeId, Sender<Packet>>,
+    ) -> Box<dyn Node>
+    + Send
+    + Sync;
+
+/// Implementation given to a client or server that doesn't request one
+pub const DEFAULT_CHAT_CLIENT: &str = "chat_client";
+pub const DEFAULT_MEDIA_CLIENT: &str = "media_client";
+pub const DEFAULT_CONTENT_SERVER: &str = "content_server";
+pub const DEFAULT_COMMUNICATION_SERVER: &str = "communication_server";
+
+/// Implementation given to a client of `kind` that doesn't request one
+pub fn default_client_implementation(kind: ClientKind) -> &'static str {
+    match kind {
+        ClientKind::Chat => DEFAULT_CHAT_CLIENT,
+        ClientKind::Media => DEFAULT_MEDIA_CLIENT,
+    }
+}
+
+/// Implementation given to a server of `kind` that doesn't request one
+pub fn default_server_implementation(kind: ServerKind) -> &'static str {
+    match kind {
+        ServerKind::Text | ServerKind::Media => DEFAULT_CONTENT_SERVER,
+        ServerKind::Communication => DEFAULT_COMMUNICATION_SERVER,
+    }
+}
+
+/// Implementations of one kind of client or server, by name
+struct Factories<C, E> {
+    factories: HashMap<String, Arc<NodeFactoryFn<C, E>>>,
+}
+
+impl<C, E> Factories<C, E> {
+    fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    fn insert<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn(
+                NodeId,
+                Sender<E>,
+                Receiver<C>,
+                Receiver<Packet>,
+                HashMap<NodeId, Sender<Packet>>,
+            ) -> Box<dyn Node>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.factories.insert(name.into(), Arc::new(factory));
+    }
+
+    fn get(&self, name: &str) -> Option<&NodeFactoryFn<C, E>> {
+        self.factories.get(name).map(Arc::as_ref)
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+// Derived `Clone` would require `C: Clone` and `E: Clone`
+impl<C, E> Clone for Factories<C, E> {
+    fn clone(&self) -> Self {
+        Self {
+            factories: self.factories.clone(),
+        }
+    }
+}
+
+/// Client implementations available to the network, by name, for each
+/// client kind.
+///
+/// A kind fixes the commands and events the simulation controller exchanges
+/// with the client, an implementation only has to speak them.
+/// `ClientRegistry::default()` holds `ChatClient` under
+/// [`DEFAULT_CHAT_CLIENT`] and `MediaClient` under [`DEFAULT_MEDIA_CLIENT`].
+#[derive(Clone)]
+pub struct ClientRegistry {
+    chat: Factories<ChatClientCommand, ChatClientEvent>,
+    media: Factories<MediaClientCommand, MediaClientEvent>,
+}
+
+impl ClientRegistry {
+    /// Registry without any implementation
+    pub fn empty() -> Self {
+        Self {
+            chat: Factories::new(),
+            media: Factories::new(),
+        }
+    }
+
+    /// Make `factory` available to chat clients under `name`, replacing
+    /// what was registered there
+    pub fn register_chat<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn(
+                NodeId,
+                Sender<ChatClientEvent>,
+                Receiver<ChatClientCommand>,
+                Receiver<Packet>,
+                HashMap<NodeId, Sender<Packet>>,
+            ) -> Box<dyn Node>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.chat.insert(name, factory);
+        self
+    }
+
+    /// Make `factory` available to media clients under `name`, replacing
+    /// what was registered there
+    pub fn register_media<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn(
+                NodeId,
+                Sender<MediaClientEvent>,
+                Receiver<MediaClientCommand>,
+                Receiver<Packet>,
+                HashMap<NodeId, Sender<Packet>>,
+            ) -> Box<dyn Node>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.media.insert(name, factory);
+        self
+    }
+
+    /// Chat client factory registered under `name`
+    pub fn chat(&self, name: &str) -> Option<&NodeFactoryFn<ChatClientCommand, ChatClientEvent>> {
+        self.chat.get(name)
+    }
+
+    /// Media client factory registered under `name`
+    pub fn media(
+        &self,
+        name: &str,
+    ) -> Option<&NodeFactoryFn<MediaClientCommand, MediaClientEvent>> {
+        self.media.get(name)
+    }
+
+    /// Whether something is registered under `name` for clients of `kind`
+    pub fn contains(&self, kind: ClientKind, name: &str) -> bool {
+        match kind {
+            ClientKind::Chat => self.chat.contains(name),
+            ClientKind::Media => self.media.contains(name),
+        }
+    }
+
+    /// Names of every chat client implementation, sorted
+    pub fn chat_names(&self) -> Vec<&str> {
+        self.chat.names()
+    }
+
+    /// Names of every media client implementation, sorted
+    pub fn media_names(&self) -> Vec<&str> {
+        self.media.names()
+    }
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_chat(
+            DEFAULT_CHAT_CLIENT,
+            |id, event_send, command_recv, packet_recv, packet_send| {
+                Box::new(ChatClient::new(
+                    id,
+                    event_send,
+                    command_recv,
+                    packet_recv,
+                    packet_send,
+                ))
+            },
+        );
+        registry.register_media(
+            DEFAULT_MEDIA_CLIENT,
+            |id, event_send, command_recv, packet_recv, packet_send| {
+                Box::new(MediaClient::new(
+                    id,
+                    event_send,
+                    command_recv,
+                    packet_recv,
+                    packet_send,
+                ))
+            },
+        );
+        registry
+    }
+}
+
+/// Server implementations available to the network, by name, for each
+/// server kind.
+///
+/// Text and media servers speak the same commands and events, but are
+/// registered separately since an implementation usually serves only one
+/// of them. `ServerRegistry::default()` holds `ContentServer` for text and
+/// media under [`DEFAULT_CONTENT_SERVER`] and `CommunicationServer` under
+/// [`DEFAULT_COMMUNICATION_SERVER`].
+#[derive(Clone)]
+pub struct ServerRegistry {
+    text: Factories<ContentServerCommand, ContentServerEvent>,
+    media: Factories<ContentServerCommand, ContentServerEvent>,
+    communication: Factories<CommunicationServerCommand, CommunicationServerEvent>,
+}
+
+impl ServerRegistry {
+    /// Registry without any implementation
+    pub fn empty() -> Self {
+        Self {
+            text: Factories::new(),
+            media: Factories::new(),
+            communication: Factories::new(),
+        }
+    }
+
+    /// Make `factory` available to text servers under `name`, replacing
+    /// what was registered there
+    pub fn register_text<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn(
+                NodeId,
+                Sender<ContentServerEvent>,
+                Receiver<ContentServerCommand>,
+                Receiver<Packet>,
+                HashMap<NodeId, Sender<Packet>>,
+            ) -> Box<dyn Node>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.text.insert(name, factory);
+        self
+    }
+
+    /// Make `factory` available to media servers under `name`, replacing
+    /// what was registered there
+    pub fn register_media<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn(
+                NodeId,
+                Sender<ContentServerEvent>,
+                Receiver<ContentServerCommand>,
+                Receiver<Packet>,
+                HashMap<NodeId, Sender<Packet>>,
+            ) -> Box<dyn Node>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.media.insert(name, factory);
+        self
+    }
+
+    /// Make `factory` available to communication servers under `name`,
+    /// replacing what was registered there
+    pub fn register_communication<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn(
+                NodeId,
+                Sender<CommunicationServerEvent>,
+                Receiver<CommunicationServerCommand>,
+                Receiver<Packet>,
+                HashMap<NodeId, Sender<Packet>>,
+            ) -> Box<dyn Node>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.communication.insert(name, factory);
+        self
+    }
+
+    /// Text server factory registered under `name`
+    pub fn text(
+        &self,
+        name: &str,
+    ) -> Option<&NodeFactoryFn<ContentServerCommand, ContentServerEvent>> {
+        self.text.get(name)
+    }
+
+    /// Media server factory registered under `name`
+    pub fn media(
+        &self,
+        name: &str,
+    ) -> Option<&NodeFactoryFn<ContentServerCommand, ContentServerEvent>> {
+        self.media.get(name)
+    }
+
+    /// Communication server factory registered under `name`
+    pub fn communication(
+        &self,
+        name: &str,
+    ) -> Option<&NodeFactoryFn<CommunicationServerCommand, CommunicationServerEvent>> {
+        self.communication.get(name)
+    }
+
+    /// Whether something is registered under `name` for servers of `kind`
+    pub fn contains(&self, kind: ServerKind, name: &str) -> bool {
+        match kind {
+            ServerKind::Text => self.text.contains(name),
+            ServerKind::Media => self.media.contains(name),
+            ServerKind::Communication => self.communication.contains(name),
+        }
+    }
+
+    /// Names of every text server implementation, sorted
+    pub fn text_names(&self) -> Vec<&str> {
+        self.text.names()
+    }
+
+    /// Names of every media server implementation, sorted
+    pub fn media_names(&self) -> Vec<&str> {
+        self.media.names()
+    }
+
+    /// Names of every communication server implementation, sorted
+    pub fn communication_names(&self) -> Vec<&str> {
+        self.communication.names()
+    }
+}
+
+impl Default for ServerRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_text(
+            DEFAULT_CONTENT_SERVER,
+            |id, event_send, command_recv, packet_recv, packet_send| {
+                Box::new(ContentServer::new(
+                    id,
+                    packet_recv,
+                    packet_send,
+                    event_send,
+                    command_recv,
+                    ServerType::Text,
+                ))
+            },
+        );
+        registry.register_media(
+            DEFAULT_CONTENT_SERVER,
+            |id, event_send, command_recv, packet_recv, packet_send| {
+                Box::new(ContentServer::new(
+                    id,
+                    packet_recv,
+                    packet_send,
+                    event_send,
+                    command_recv,
+                    ServerType::Media,
+                ))
+            },
+        );
+        registry.register_communication(
+            DEFAULT_COMMUNICATION_SERVER,
+            |id, event_send, command_recv, packet_recv, packet_send| {
+                Box::new(CommunicationServer::new(
+                    id,
+                    packet_recv,
+                    packet_send,
+                    event_send,
+                    command_recv,
+                ))
+            },
+        );
+        registry
+    }
+}
+
+/// Every registry the network takes its node implementations from, along
+/// with the factory of its simulation controller
+#[derive(Clone, Default)]
+pub struct Registries {
+    pub drones: DroneRegistry,
+    pub clients: ClientRegistry,
+    pub servers: ServerRegistry,
+    pub controller: ControllerFactory,
+}
+
+impl From<DroneRegistry> for Registries {
+    /// Custom drones, default clients and servers
+    fn from(drones: DroneRegistry) -> Self {
+        Self {
+            drones,
+            ..Self::default()
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Adding and removing drones while the network runs.
+//!
+//! The config file is polled for changes. On each change the new drones are
+//! built with fresh channels and spawned, and every running drone they list
+//! as a neighbor gets their packet sender through `DroneCommand::AddSender`.
+//! Drones no longer in the file are crashed and removed from their
+//! neighbors. Clients and servers can't be rewired once spawned, so changes
+//! to them are only reported, as are new edges between drones that were
+//! already running.
+//!
+//! Like the drone supervisor, the reloader keeps every node's packet sender
+//! and so only runs until the network is told to stop.
+
+use colored::Colorize;
+use crossbeam_channel::{unbounded, Sender};
+use log::{info, warn};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use wg_2024::{
+    config::Drone as ConfigDrone,
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+    packet::Packet,
+};
+
+use crate::{
+    config::NodeKind,
+    error::{NetworkInitError, NodePanic},
+    link::LinkSenders,
+    network_initializer::{
+        choose_implementations, omit_nodes, open_as, packet_channel, spawn_named, RunOptions,
+    },
+    registry::DroneRegistry,
+    shutdown::Shutdown,
+    topology::{build_topology, LiveTopology, Topology},
+    validation,
+};
+
+/// How often the config file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What's needed to wire new drones into a running network
+pub struct ConfigReloader {
+    registry: DroneRegistry,
+    options: RunOptions,
+    event_send: Sender<DroneEvent>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    /// Links of the delayed edges of the initial config
+    link_send: LinkSenders,
+    /// Command senders of the running drones
+    command_send: HashMap<NodeId, Sender<DroneCommand>>,
+    /// Topology the running network was built from, updated on every reload
+    topology: Topology,
+    /// Topology following the commands, told about added and removed drones
+    live_topology: LiveTopology,
+    /// Threads of the drones added by the reloader
+    spawned: Vec<(NodeId, JoinHandle<()>)>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        registry: DroneRegistry,
+        options: RunOptions,
+        event_send: Sender<DroneEvent>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        link_send: LinkSenders,
+        command_send: HashMap<NodeId, Sender<DroneCommand>>,
+        topology: Topology,
+        live_topology: LiveTopology,
+    ) -> Self {
+        Self {
+            registry,
+            options,
+            event_send,
+            packet_send,
+            link_send,
+            command_send,
+            topology,
+            live_topology,
+            spawned: Vec::new(),
+        }
+    }
+
+    /// Watch the config at `path` on a new thread until `shutdown` is
+    /// triggered, then wait for the drones it added. Returns their panics.
+    pub fn watch(
+        mut self,
+        path: PathBuf,
+        shutdown: Shutdown,
+    ) -> Result<JoinHandle<Vec<NodePanic>>, NetworkInitError> {
+        spawn_named("config-watcher".to_string(), move || {
+            let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+            let mut last: Option<SystemTime> = modified(&path);
+
+            while !shutdown.is_triggered() {
+                thread::sleep(POLL_INTERVAL);
+                let current = modified(&path);
+                if current == last {
+                    continue;
+                }
+                last = current;
+
+                info!(
+                    "[ {} ] Config changed, reloading {}",
+                    "Network Initializer".green(),
+                    path.display()
+                );
+                if let Err(err) = self.reload(&path) {
+                    warn!(
+                        "[ {} ] Unable to reload the config: {err}",
+                        "Network Initializer".yellow()
+                    );
+                }
+            }
+
+            // The shutdown only knows the drones of the initial config
+            for (id, _) in &self.spawned {
+                if let Some(command_send) = self.command_send.get(id) {
+                    let _ = command_send.send(DroneCommand::Crash);
+                    for neighbor in self.topology.neighbors(*id) {
+                        let _ = command_send.send(DroneCommand::RemoveSender(*neighbor));
+                    }
+                }
+            }
+
+            // Release every sender so the network can wind down
+            let spawned = std::mem::take(&mut self.spawned);
+            drop(self);
+
+            spawned
+                .into_iter()
+                .filter_map(|(id, handle)| handle.join().err().map(|payload| (id, payload)))
+                .map(|(id, payload)| NodePanic::new(format!("drone {id}"), payload.as_ref()))
+                .collect()
+        })
+    }
+
+    /// Apply the config at `path` to the running network
+    fn reload(&mut self, path: &Path) -> Result<(), NetworkInitError> {
+        let mut network_config =
+            open_as(path, self.options.config_format, self.options.expand_env)?;
+        omit_nodes(&mut network_config, &self.options);
+        validation::validate(&network_config.config)?;
+        validation::validate_id_space(&network_config.config, self.options.id_policy)?;
+        let topology = build_topology(&network_config.config);
+
+        // Nodes that are neither running drones nor new drones can't change
+        for id in topology.nodes() {
+            let was = self.topology.kind(id);
+            let is = topology.kind(id);
+            if is != was && (is != Some(NodeKind::Drone) || was.is_some()) {
+                warn!(
+                    "[ {} ] Node {id} changed, only drones can be added at runtime",
+                    "Network Initializer".yellow()
+                );
+            }
+        }
+
+        let implementations = choose_implementations(&network_config, &self.options)?;
+        for drone in &network_config.config.drone {
+            if self.topology.kind(drone.id).is_none() {
+                let implementation = implementations.get(&drone.id).cloned().unwrap_or_default();
+                self.add_drone(drone, &implementation)?;
+            }
+        }
+
+        for id in self.topology.nodes_of_kind(NodeKind::Drone) {
+            if topology.kind(id) != Some(NodeKind::Drone) {
+                self.remove_drone(id);
+            }
+        }
+
+        self.topology = topology;
+        Ok(())
+    }
+
+    /// Build and spawn `drone`, then hand its packet sender to its neighbors
+    fn add_drone(
+        &mut self,
+        drone: &ConfigDrone,
+        implementation: &str,
+    ) -> Result<(), NetworkInitError> {
+        let factory = self.registry.get(implementation).ok_or_else(|| {
+            NetworkInitError::UnknownDroneImplementation {
+                drone: drone.id,
+                implementation: implementation.to_string(),
+            }
+        })?;
+
+        let mut drone = drone.clone();
+        if let Some((min, max)) = self.options.pdr_range {
+            drone.pdr = rand::rng().random_range(min..=max);
+        }
+        if let Some(pdr) = self.options.pdr_override {
+            drone.pdr = pdr;
+        }
+
+        let (pkt_send, pkt_recv) = packet_channel(self.options.channel_capacity);
+        let (cmd_send, cmd_recv) = unbounded::<DroneCommand>();
+        let mut new_drone = factory(
+            &drone,
+            &self.event_send,
+            &HashMap::from([(drone.id, cmd_recv)]),
+            &self.link_send.senders_of(drone.id, &self.packet_send),
+            &HashMap::from([(drone.id, pkt_recv)]),
+        )?;
+
+        let id = drone.id;
+        let handle = spawn_named(format!("drone-{id}"), move || new_drone.run())?;
+        info!(
+            "[ {} ] [ Drone {id} ] added, uses {implementation}",
+            "Network Initializer".green()
+        );
+
+        self.live_topology.add_drone(id, &drone.connected_node_ids);
+        for neighbor in &drone.connected_node_ids {
+            match self.command_send.get(neighbor) {
+                Some(command_send) => {
+                    let command = DroneCommand::AddSender(id, pkt_send.clone());
+                    self.live_topology.record(*neighbor, &command);
+                    let _ = command_send.send(command);
+                }
+                None => warn!(
+                    "[ {} ] [ Drone {id} ] can't be added to the neighbors of node {neighbor}, only running drones can be rewired",
+                    "Network Initializer".yellow()
+                ),
+            }
+        }
+
+        self.packet_send.insert(id, pkt_send);
+        self.command_send.insert(id, cmd_send);
+        self.spawned.push((id, handle));
+        Ok(())
+    }
+
+    /// Crash drone `id` and remove it from its neighbors
+    fn remove_drone(&mut self, id: NodeId) {
+        if let Some(command_send) = self.command_send.remove(&id) {
+            let _ = command_send.send(DroneCommand::Crash);
+        }
+        self.live_topology.record(id, &DroneCommand::Crash);
+        self.packet_send.remove(&id);
+
+        for neighbor in self.topology.neighbors(id) {
+            if let Some(command_send) = self.command_send.get(neighbor) {
+                let command = DroneCommand::RemoveSender(id);
+                self.live_topology.record(*neighbor, &command);
+                let _ = command_send.send(command);
+            }
+        }
+        info!(
+            "[ {} ] [ Drone {id} ] removed",
+            "Network Initializer".green()
+        );
+    }
+}
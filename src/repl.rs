@@ -0,0 +1,194 @@
+//! Typing commands to a running network from the standard input.
+//!
+//! Each line is one command, sent through the same [`NodeCommands`] a
+//! program would use: the drone commands, and chat messages sent by a chat
+//! client through a communication server. A malformed line or an unknown
+//! node only prints an error, the prompt keeps going.
+//!
+//! `pause` and `resume` freeze the packet traffic of a network built
+//! `pausable`, see [`crate::pause`]. The GUI has no button for them until the
+//! `messages` crate has a pause command: its `GUICommands` come from there,
+//! and the simulation controller consumes every one it gets.
+
+use colored::Colorize;
+use std::io::{self, BufRead};
+
+use wg_2024::{controller::DroneCommand, network::NodeId};
+
+use crate::{
+    commands::NodeCommands,
+    error::NetworkInitError,
+    network_initializer::{pause_relays, spawn_named},
+    shutdown::Shutdown,
+    topology::Topology,
+};
+
+const HELP: &str = "\
+commands:
+  crash <drone>              crash the drone
+  pdr <drone> <rate>         set the packet drop rate of the drone, within [0.0, 1.0]
+  remove <drone> <neighbor>  make the drone drop its sender to the neighbor
+  msg <client> <server> <text>
+                             have the chat client send the text through the communication server
+  topology                   show the neighbors every node has now
+  pause                      hold every packet on its way to a node, pausable networks only
+  resume                     deliver the held packets and let the traffic go on
+  stop                       stop the whole network, like Ctrl-C
+  help                       show this list";
+
+/// One line of the prompt
+enum Line {
+    Drone(NodeId, DroneCommand),
+    Message {
+        client: NodeId,
+        server: NodeId,
+        text: String,
+    },
+    Topology,
+    Pause,
+    Resume,
+    Stop,
+    Help,
+    Empty,
+}
+
+fn node_id(arg: Option<&str>, what: &str) -> Result<NodeId, String> {
+    let arg = arg.ok_or_else(|| format!("missing {what} id"))?;
+    arg.parse()
+        .map_err(|_| format!("invalid {what} id {arg}, expected 0 to {}", NodeId::MAX))
+}
+
+/// What follows the first `count` words of `line`, spaces within kept as typed
+fn rest_after_words(line: &str, count: usize) -> &str {
+    let mut rest = line.trim_start();
+    for _ in 0..count {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+    rest.trim_end()
+}
+
+fn parse(line: &str) -> Result<Line, String> {
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Ok(Line::Empty);
+    };
+    let parsed = match command {
+        "crash" => Line::Drone(node_id(words.next(), "drone")?, DroneCommand::Crash),
+        "pdr" => {
+            let drone = node_id(words.next(), "drone")?;
+            let rate = words.next().ok_or("missing packet drop rate")?;
+            let rate: f32 = rate
+                .parse()
+                .ok()
+                .filter(|rate| (0.0..=1.0).contains(rate))
+                .ok_or_else(|| format!("invalid packet drop rate {rate}"))?;
+            Line::Drone(drone, DroneCommand::SetPacketDropRate(rate))
+        }
+        "remove" => {
+            let drone = node_id(words.next(), "drone")?;
+            let neighbor = node_id(words.next(), "neighbor")?;
+            Line::Drone(drone, DroneCommand::RemoveSender(neighbor))
+        }
+        "msg" => {
+            // `client5` and `5` name the same node
+            let client = words.next().map(|w| w.strip_prefix("client").unwrap_or(w));
+            let client = node_id(client, "client")?;
+            let server = words.next().map(|w| w.strip_prefix("server").unwrap_or(w));
+            let server = node_id(server, "server")?;
+            let text = rest_after_words(line, 3);
+            if text.is_empty() {
+                return Err("missing message text".to_string());
+            }
+            return Ok(Line::Message {
+                client,
+                server,
+                text: text.to_string(),
+            });
+        }
+        "topology" => Line::Topology,
+        "pause" => Line::Pause,
+        "resume" => Line::Resume,
+        "stop" => Line::Stop,
+        "help" => Line::Help,
+        _ => return Err(format!("unknown command {command}, try help")),
+    };
+    match words.next() {
+        Some(extra) => Err(format!("unexpected {extra} after {command}")),
+        None => Ok(parsed),
+    }
+}
+
+/// One line per node with its neighbors, sorted
+fn print_topology(topology: &Topology) {
+    for node in topology.nodes() {
+        let mut neighbors = topology.neighbors(node).to_vec();
+        neighbors.sort_unstable();
+        let kind = topology
+            .kind(node)
+            .map_or_else(String::new, |kind| format!("{kind:?} "));
+        println!("{kind}{node}: {neighbors:?}");
+    }
+}
+
+/// Read commands from the standard input on a thread of its own and send
+/// them with `commands`. `pausable` tells whether the network has the relays
+/// `pause` needs. The thread ends at the end of the input or on `stop`. A
+/// blocked read can't be interrupted, it isn't joined.
+pub(crate) fn spawn(
+    commands: NodeCommands,
+    shutdown: Shutdown,
+    pausable: bool,
+) -> Result<(), NetworkInitError> {
+    spawn_named("repl".to_string(), move || {
+        println!("{HELP}");
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if shutdown.is_triggered() {
+                break;
+            }
+            let result = match parse(&line) {
+                Ok(Line::Drone(drone, command)) => commands
+                    .send_drone_command(drone, command)
+                    .map_err(|err| err.to_string()),
+                Ok(Line::Message {
+                    client,
+                    server,
+                    text,
+                }) => commands
+                    .send_chat_message(client, server, text)
+                    .map_err(|err| err.to_string()),
+                Ok(Line::Topology) => {
+                    print_topology(&commands.topology());
+                    Ok(())
+                }
+                Ok(Line::Pause) => {
+                    pause_relays(&shutdown, pausable);
+                    Ok(())
+                }
+                Ok(Line::Resume) => {
+                    shutdown.pause_switch().resume();
+                    println!("resumed");
+                    Ok(())
+                }
+                Ok(Line::Stop) => {
+                    shutdown.trigger();
+                    break;
+                }
+                Ok(Line::Help) => {
+                    println!("{HELP}");
+                    Ok(())
+                }
+                Ok(Line::Empty) => Ok(()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                eprintln!("[ {} ] {err}", "REPL".red());
+            }
+        }
+    })?;
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Keeping the event channels of clients and servers open once the
+//! simulation controller is gone.
+//!
+//! The controller holds the only receiver of each event channel. When it
+//! panics the receivers are dropped, and every node sending an event from
+//! then on gets an error, which most implementations unwrap. The sentinel
+//! holds a clone of each receiver without reading it while the controller
+//! runs, so it takes nothing from it, then drains them all once the
+//! controller is gone. Drone events go through the event tee, which keeps
+//! draining them the same way.
+
+use colored::Colorize;
+use crossbeam_channel::{Receiver, Select};
+use log::warn;
+
+use messages::{
+    client_commands::{ChatClientEvent, MediaClientEvent},
+    server_commands::{CommunicationServerEvent, ContentServerEvent},
+};
+
+/// Spare receiver of every client and server event channel
+pub struct EventSentinel {
+    pub chat_client: Receiver<ChatClientEvent>,
+    pub media_client: Receiver<MediaClientEvent>,
+    pub communication_server: Receiver<CommunicationServerEvent>,
+    pub text_server: Receiver<ContentServerEvent>,
+    pub media_server: Receiver<ContentServerEvent>,
+}
+
+impl EventSentinel {
+    /// Wait until `gone_recv` reports that the controller is gone, then
+    /// receive and drop every event until all the nodes are gone. Returns
+    /// right away when `gone_recv` disconnects without a report, the
+    /// controller never went away then.
+    pub fn watch(self, gone_recv: &Receiver<()>) {
+        if gone_recv.recv().is_err() {
+            return;
+        }
+        warn!(
+            "[ {} ] Simulation Controller is gone, client and server events are dropped from now on",
+            "Network Initializer".yellow()
+        );
+
+        let mut select = Select::new();
+        let chat_client = select.recv(&self.chat_client);
+        let media_client = select.recv(&self.media_client);
+        let communication_server = select.recv(&self.communication_server);
+        let text_server = select.recv(&self.text_server);
+        let media_server = select.recv(&self.media_server);
+
+        let mut open = 5;
+        while open > 0 {
+            let operation = select.select();
+            let index = operation.index();
+            let disconnected = if index == chat_client {
+                operation.recv(&self.chat_client).is_err()
+            } else if index == media_client {
+                operation.recv(&self.media_client).is_err()
+            } else if index == communication_server {
+                operation.recv(&self.communication_server).is_err()
+            } else if index == text_server {
+                operation.recv(&self.text_server).is_err()
+            } else {
+                debug_assert_eq!(index, media_server);
+                operation.recv(&self.media_server).is_err()
+            };
+
+            // A disconnected channel is always ready, stop selecting it
+            if disconnected {
+                select.remove(index);
+                open -= 1;
+            }
+        }
+    }
+}
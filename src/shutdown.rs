@@ -0,0 +1,294 @@
+//! Stopping a running network from outside its threads.
+//!
+//! Each node type is stopped differently:
+//! * drones get a `DroneCommand::Crash`, then a `DroneCommand::RemoveSender`
+//!   for each of their neighbors so they drop the senders keeping the other
+//!   drones' packet channels open;
+//! * clients and servers have no stop command in `messages`, they stop when
+//!   their channels disconnect, that is once the drones around them and the
+//!   simulation controller are gone;
+//! * the GUI is closed through its `egui::Context`, which makes
+//!   `eframe::run_native` return.
+//!
+//! A drain can come first: no node has a command to stop accepting new
+//! packets, so it waits until the drones sent none for a quiet period, and
+//! stops the network anyway once its timeout expires.
+
+use colored::Colorize;
+use crossbeam_channel::Sender;
+use eframe::egui;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use wg_2024::{controller::DroneCommand, network::NodeId};
+
+use crate::{
+    error::NetworkInitError, network_initializer::spawn_named, pause::PauseSwitch,
+    stats::PacketCounter,
+};
+
+/// How often the stop timer checks whether the network already stopped
+const TIMER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long the drones must send nothing for a drain to end, unless the
+/// network was built with its own period
+pub const DEFAULT_DRAIN_QUIET: Duration = Duration::from_millis(500);
+/// How long a drain waits for the drones to go quiet, unless the network was
+/// built with its own timeout
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Cloneable handle that stops the whole network when triggered
+#[derive(Clone)]
+pub struct Shutdown {
+    drones: HashMap<NodeId, Sender<DroneCommand>>,
+    neighbor: HashMap<NodeId, Vec<NodeId>>,
+    gui: Arc<Mutex<Option<egui::Context>>>,
+    triggered: Arc<AtomicBool>,
+    pause: PauseSwitch,
+    // Packets sent by the drones, watched while draining
+    sent: PacketCounter,
+    quiet: Duration,
+    // Drain timeout of `stop`, which triggers right away when `None`
+    drain_on_stop: Option<Duration>,
+}
+
+impl Shutdown {
+    /// `drones` are the command senders of every drone, `neighbor` the
+    /// neighbor map of the network and `sent` the packets its drones sent
+    pub fn new(
+        drones: HashMap<NodeId, Sender<DroneCommand>>,
+        neighbor: HashMap<NodeId, Vec<NodeId>>,
+        sent: PacketCounter,
+    ) -> Self {
+        Self {
+            drones,
+            neighbor,
+            gui: Arc::new(Mutex::new(None)),
+            triggered: Arc::new(AtomicBool::new(false)),
+            pause: PauseSwitch::default(),
+            sent,
+            quiet: DEFAULT_DRAIN_QUIET,
+            drain_on_stop: None,
+        }
+    }
+
+    /// Make [`Shutdown::stop`] drain for at most `timeout`, until the drones
+    /// sent nothing for `quiet`
+    pub fn draining(mut self, quiet: Duration, timeout: Duration) -> Self {
+        self.quiet = quiet;
+        self.drain_on_stop = Some(timeout);
+        self
+    }
+
+    /// Remember the GUI context so triggering closes the window. If the
+    /// shutdown already happened, the window is closed right away.
+    pub fn set_gui_context(&self, ctx: egui::Context) {
+        // Checked under the lock so a concurrent trigger can't miss the context
+        if let Ok(mut gui) = self.gui.lock() {
+            if self.is_triggered() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            *gui = Some(ctx);
+        }
+    }
+
+    /// Switch pausing the packet relays of the network, if it has any
+    pub fn pause_switch(&self) -> &PauseSwitch {
+        &self.pause
+    }
+
+    /// Whether the network has already been told to stop
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Tell every drone to crash and close the GUI, only the first call
+    /// does anything. A paused network is resumed, or the packets queued in
+    /// the relays would keep the nodes waiting.
+    pub fn trigger(&self) {
+        if self.triggered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.pause.resume();
+        info!(
+            "[ {} ] Shutting down the network",
+            "Network Initializer".green()
+        );
+
+        // A drone that already exited can't receive, that's fine
+        for command_send in self.drones.values() {
+            let _ = command_send.send(DroneCommand::Crash);
+        }
+        for (id, command_send) in &self.drones {
+            for neighbor in self.neighbor.get(id).into_iter().flatten() {
+                let _ = command_send.send(DroneCommand::RemoveSender(*neighbor));
+            }
+        }
+
+        match self.gui.lock() {
+            Ok(gui) => {
+                if let Some(ctx) = gui.as_ref() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+            Err(_) => warn!(
+                "[ {} ] Unable to close the GUI",
+                "Network Initializer".yellow()
+            ),
+        }
+    }
+
+    /// Wait until the drones sent no packet for the quiet period, at most
+    /// `timeout`, then trigger. Returns whether they went quiet in time.
+    pub fn drain(&self, timeout: Duration) -> bool {
+        info!(
+            "[ {} ] Draining, waiting for {:?} without packets",
+            "Network Initializer".green(),
+            self.quiet
+        );
+        let deadline = Instant::now() + timeout;
+        let mut last_sent = self.sent.load(Ordering::SeqCst);
+        let mut quiet_since = Instant::now();
+        let drained = loop {
+            let now = Instant::now();
+            if self.is_triggered() || now - quiet_since >= self.quiet {
+                break true;
+            }
+            if now >= deadline {
+                break false;
+            }
+            thread::sleep(TIMER_POLL_INTERVAL.min(deadline - now));
+            let sent = self.sent.load(Ordering::SeqCst);
+            if sent != last_sent {
+                (last_sent, quiet_since) = (sent, Instant::now());
+            }
+        };
+        if !drained {
+            warn!(
+                "[ {} ] The drones still send packets after {timeout:?}, stopping anyway",
+                "Network Initializer".yellow()
+            );
+        }
+        self.trigger();
+        drained
+    }
+
+    /// Stop the network as it was built to: after a drain when built
+    /// [`Shutdown::draining`], right away otherwise
+    pub fn stop(&self) {
+        match self.drain_on_stop {
+            Some(timeout) => {
+                self.drain(timeout);
+            }
+            None => self.trigger(),
+        }
+    }
+
+    /// Trigger the shutdown once `duration` has passed, from a thread of its
+    /// own. It doesn't depend on the GUI: a window opened after it fired is
+    /// closed right away. The thread ends early when the network is stopped
+    /// another way.
+    pub fn trigger_after(&self, duration: Duration) -> Result<(), NetworkInitError> {
+        let shutdown = self.clone();
+        let deadline = Instant::now() + duration;
+        spawn_named("stop-timer".to_string(), move || loop {
+            if shutdown.is_triggered() {
+                return;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                info!(
+                    "[ {} ] Ran for {duration:?}, stopping",
+                    "Network Initializer".green()
+                );
+                shutdown.stop();
+                return;
+            }
+            thread::sleep((deadline - now).min(TIMER_POLL_INTERVAL));
+        })?;
+        Ok(())
+    }
+
+    /// Block until the network is told to stop
+    pub fn wait(&self) {
+        while !self.is_triggered() {
+            thread::sleep(TIMER_POLL_INTERVAL);
+        }
+    }
+
+    /// Stop the network when the process receives Ctrl-C
+    pub fn install_ctrlc_handler(&self) {
+        Self::install_ctrlc_handler_for(vec![self.clone()]);
+    }
+
+    /// Stop all of `shutdowns` when the process receives Ctrl-C, draining
+    /// them side by side. A process has a single handler, so every network
+    /// must be given at once.
+    pub fn install_ctrlc_handler_for(shutdowns: Vec<Self>) {
+        let handler = move || {
+            thread::scope(|scope| {
+                for shutdown in &shutdowns {
+                    scope.spawn(|| shutdown.stop());
+                }
+            });
+        };
+        if let Err(err) = ctrlc::set_handler(handler) {
+            warn!(
+                "[ {} ] Unable to install the Ctrl-C handler: {err}",
+                "Network Initializer".yellow()
+            );
+        }
+    }
+}
+
+/// Shutdown of the network running now, out of several run one after the
+/// other: a process has a single Ctrl-C handler, installed once for all
+#[derive(Clone, Default)]
+pub struct ShutdownSlot {
+    current: Arc<Mutex<Option<Shutdown>>>,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl ShutdownSlot {
+    /// Make `shutdown` the one Ctrl-C triggers, right away if Ctrl-C was
+    /// already received
+    pub fn set(&self, shutdown: Shutdown) {
+        if let Ok(mut current) = self.current.lock() {
+            if self.is_interrupted() {
+                shutdown.trigger();
+            }
+            *current = Some(shutdown);
+        }
+    }
+
+    /// Whether Ctrl-C was received, no other network should start then
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+
+    /// Stop the current network when the process receives Ctrl-C
+    pub fn install_ctrlc_handler(&self) {
+        let slot = self.clone();
+        let handler = move || {
+            slot.interrupted.store(true, Ordering::SeqCst);
+            // Not held while draining, which takes a while
+            let current = slot.current.lock().ok().and_then(|current| current.clone());
+            if let Some(shutdown) = current {
+                shutdown.stop();
+            }
+        };
+        if let Err(err) = ctrlc::set_handler(handler) {
+            warn!(
+                "[ {} ] Unable to install the Ctrl-C handler: {err}",
+                "Network Initializer".yellow()
+            );
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Canonical description of a built network.
+//!
+//! Unlike the config it was built from, a snapshot holds the decisions taken
+//! while building: the implementation of every node, the kind of every
+//! client and server, the pdr after overrides. Nodes are sorted by id and
+//! neighbors too, so two configs producing the same network give identical
+//! snapshots. The field names are the ones of a config file, a snapshot can
+//! be run again as is.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use wg_2024::network::NodeId;
+
+use crate::{
+    config::{ClientKind, ConfigFormat, ServerKind},
+    network_initializer::Network,
+};
+
+/// Every node of a built network, sorted by id within each section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub drone: Vec<DroneSnapshot>,
+    pub client: Vec<ClientSnapshot>,
+    pub server: Vec<ServerSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DroneSnapshot {
+    pub id: NodeId,
+    pub connected_node_ids: Vec<NodeId>,
+    pub pdr: f32,
+    pub implementation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClientSnapshot {
+    pub id: NodeId,
+    pub connected_drone_ids: Vec<NodeId>,
+    pub kind: ClientKind,
+    pub implementation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerSnapshot {
+    pub id: NodeId,
+    pub connected_drone_ids: Vec<NodeId>,
+    pub kind: ServerKind,
+    pub implementation: String,
+}
+
+fn sorted(ids: &[NodeId]) -> Vec<NodeId> {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids
+}
+
+/// Snapshot of `network` as it was built
+pub fn snapshot(network: &Network) -> Snapshot {
+    let config = &network.config;
+    // Every node below was built, so each lookup has an entry
+    let name = |implementations: &HashMap<NodeId, String>, id: NodeId| {
+        implementations.get(&id).cloned().unwrap_or_default()
+    };
+
+    let mut drone: Vec<DroneSnapshot> = config
+        .drone
+        .iter()
+        .map(|d| DroneSnapshot {
+            id: d.id,
+            connected_node_ids: sorted(&d.connected_node_ids),
+            pdr: d.pdr,
+            implementation: name(&network.implementations, d.id),
+        })
+        .collect();
+    drone.sort_unstable_by_key(|d| d.id);
+
+    let mut client: Vec<ClientSnapshot> = config
+        .client
+        .iter()
+        .filter_map(|c| {
+            Some(ClientSnapshot {
+                id: c.id,
+                connected_drone_ids: sorted(&c.connected_drone_ids),
+                kind: *network.client_kinds.get(&c.id)?,
+                implementation: name(&network.client_implementations, c.id),
+            })
+        })
+        .collect();
+    client.sort_unstable_by_key(|c| c.id);
+
+    let mut server: Vec<ServerSnapshot> = config
+        .server
+        .iter()
+        .filter_map(|s| {
+            Some(ServerSnapshot {
+                id: s.id,
+                connected_drone_ids: sorted(&s.connected_drone_ids),
+                kind: *network.server_kinds.get(&s.id)?,
+                implementation: name(&network.server_implementations, s.id),
+            })
+        })
+        .collect();
+    server.sort_unstable_by_key(|s| s.id);
+
+    Snapshot {
+        drone,
+        client,
+        server,
+    }
+}
+
+/// `snapshot` written in `format`
+pub fn serialize(snapshot: &Snapshot, format: ConfigFormat) -> String {
+    // Only scalars and arrays of scalars, no format can reject that
+    match format {
+        // Only configs have a binary form, a `.bin` path gets TOML
+        ConfigFormat::Toml | ConfigFormat::Binary => {
+            toml::to_string(snapshot).expect("a snapshot is valid TOML")
+        }
+        ConfigFormat::Json => {
+            let mut json =
+                serde_json::to_string_pretty(snapshot).expect("a snapshot is valid JSON");
+            json.push('\n');
+            json
+        }
+        ConfigFormat::Yaml => serde_yaml::to_string(snapshot).expect("a snapshot is valid YAML"),
+    }
+}
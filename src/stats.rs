@@ -0,0 +1,330 @@
+//! Traffic handled by each drone during a simulation.
+//!
+//! Drones report to the simulation controller through a single `DroneEvent`
+//! channel. The initializer sits in the middle of it: every event is counted
+//! and forwarded untouched, so the controller sees exactly what it would
+//! without the counter.
+//!
+//! The same tee feeds a network-wide packet counter, sampled once a second
+//! by the throughput meter, and hands a copy of every event to the event
+//! observer, if any, and to every tap subscribed at the time.
+//!
+//! The server event streams have no tee of their own: a tap given in the
+//! [`EventTaps`] of the options puts a thread in the middle of its stream,
+//! copying every event before passing it on.
+
+use colored::Colorize;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use log::{info, warn};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use wg_2024::{controller::DroneEvent, network::NodeId, packet::Packet};
+
+use messages::server_commands::{CommunicationServerEvent, ContentServerEvent};
+
+use crate::shutdown::Shutdown;
+
+/// Packets sent and dropped by one drone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacketStats {
+    pub sent: u64,
+    pub dropped: u64,
+    /// Sent packets the drone didn't start itself, i.e. not its nacks
+    pub forwarded: u64,
+}
+
+/// Stats of every drone that reported at least one event, shared between
+/// the counting thread and whoever reads them
+pub type StatsTable = Arc<Mutex<HashMap<NodeId, PacketStats>>>;
+
+/// Packets sent by any drone so far, shared the same way
+pub type PacketCounter = Arc<AtomicU64>;
+
+/// Callback given every `DroneEvent` of the network, on a thread of its own
+/// so a slow one can't hold back the simulation controller
+#[derive(Clone)]
+pub struct EventObserver(Arc<dyn Fn(&DroneEvent) + Send + Sync>);
+
+impl EventObserver {
+    pub fn new(observer: impl Fn(&DroneEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(observer))
+    }
+
+    /// Call the observer on every event of `event_recv`, until all its
+    /// senders are gone
+    pub fn observe(&self, event_recv: &Receiver<DroneEvent>) {
+        for event in event_recv {
+            (self.0)(&event);
+        }
+    }
+}
+
+impl fmt::Debug for EventObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventObserver")
+    }
+}
+
+/// Channels given a copy of every event of a stream before the simulation
+/// controller gets it, for tools watching the network from outside. A tap
+/// whose receiver is gone stops getting events.
+#[derive(Debug, Clone, Default)]
+pub struct EventTaps {
+    pub comm: Option<Sender<CommunicationServerEvent>>,
+    pub text: Option<Sender<ContentServerEvent>>,
+    pub media: Option<Sender<ContentServerEvent>>,
+    pub drone: Option<Sender<DroneEvent>>,
+}
+
+impl EventTaps {
+    /// Number of server streams tapped, each needs its own thread
+    pub(crate) fn server_taps(&self) -> usize {
+        usize::from(self.comm.is_some())
+            + usize::from(self.text.is_some())
+            + usize::from(self.media.is_some())
+    }
+}
+
+/// Subscriptions to the `DroneEvent` stream, taken while the network runs
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers(Arc<Mutex<Vec<Sender<DroneEvent>>>>);
+
+impl Subscribers {
+    /// Receiver of every event from now on, dropping it ends the
+    /// subscription
+    pub(crate) fn subscribe(&self) -> Receiver<DroneEvent> {
+        let (tap_send, tap_recv) = unbounded();
+        self.add(tap_send);
+        tap_recv
+    }
+
+    /// Send every event from now on to `tap_send`
+    pub(crate) fn add(&self, tap_send: Sender<DroneEvent>) {
+        if let Ok(mut taps) = self.0.lock() {
+            taps.push(tap_send);
+        }
+    }
+
+    /// Copy `event` to every tap still subscribed
+    fn send(&self, event: &DroneEvent) {
+        if let Ok(mut taps) = self.0.lock() {
+            taps.retain(|tap_send| tap_send.send(event.clone()).is_ok());
+        }
+    }
+}
+
+/// How often the throughput meter logs
+const METER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Drone that emitted the event about `packet`. Drones advance `hop_index`
+/// before sending or dropping, so it's the hop before the current one.
+fn reporting_drone(packet: &Packet) -> Option<NodeId> {
+    let header = &packet.routing_header;
+    header
+        .hop_index
+        .checked_sub(1)
+        .and_then(|index| header.hops.get(index))
+        .copied()
+}
+
+/// Count every event of `event_recv` into `stats` and `sent`, copy it to
+/// `observer_send` if any and to `taps`, then forward it to `event_send`.
+/// Returns once all the senders of `event_recv` are gone.
+///
+/// When the receiving end of `event_send` is gone, the simulation controller
+/// is: that's reported once on `gone_send`, and the events are still counted
+/// and received so the drones sending them don't fail.
+pub fn tee(
+    event_recv: &Receiver<DroneEvent>,
+    event_send: &Sender<DroneEvent>,
+    stats: &StatsTable,
+    sent: &PacketCounter,
+    observer_send: Option<&Sender<DroneEvent>>,
+    taps: &Subscribers,
+    gone_send: &Sender<()>,
+) {
+    let mut controller_gone = false;
+    for event in event_recv {
+        let counted = match &event {
+            DroneEvent::PacketSent(packet) => reporting_drone(packet).map(|id| {
+                // A packet a drone starts has the drone as its first hop
+                let forwarded = packet.routing_header.hops.first() != Some(&id);
+                (id, true, forwarded)
+            }),
+            DroneEvent::PacketDropped(packet) => {
+                reporting_drone(packet).map(|id| (id, false, false))
+            }
+            DroneEvent::ControllerShortcut(_) => None,
+        };
+
+        if matches!(event, DroneEvent::PacketSent(_)) {
+            sent.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some((id, was_sent, forwarded)) = counted {
+            if let Ok(mut stats) = stats.lock() {
+                let entry = stats.entry(id).or_default();
+                if was_sent {
+                    entry.sent += 1;
+                } else {
+                    entry.dropped += 1;
+                }
+                entry.forwarded += u64::from(forwarded);
+            }
+        }
+
+        // The observer channel is unbounded, a slow observer only makes it grow.
+        // An observer that panicked just stops getting events.
+        if let Some(observer_send) = observer_send {
+            let _ = observer_send.send(event.clone());
+        }
+        taps.send(&event);
+        if !controller_gone && event_send.send(event).is_err() {
+            controller_gone = true;
+            warn!(
+                "[ {} ] Simulation Controller is gone, drone events are only counted from now on",
+                "Network Initializer".yellow()
+            );
+            let _ = gone_send.send(());
+        }
+    }
+}
+
+/// Copy every event of `event_recv` to `tap_send`, then forward it to
+/// `event_send`. Returns once all the senders of `event_recv` are gone.
+pub fn tap<T: Clone>(event_recv: &Receiver<T>, tap_send: &Sender<T>, event_send: &Sender<T>) {
+    let mut tap_gone = false;
+    for event in event_recv {
+        if !tap_gone {
+            tap_gone = tap_send.send(event.clone()).is_err();
+        }
+        // The sentinel drains the stream once the controller is gone
+        let _ = event_send.send(event);
+    }
+}
+
+/// Log the packets sent per second every `METER_INTERVAL`, until the
+/// network is told to stop or the tee feeding `sent` is gone
+pub fn meter(sent: &PacketCounter, shutdown: &Shutdown) {
+    let mut last_count = sent.load(Ordering::Relaxed);
+    let mut last_sample = Instant::now();
+
+    loop {
+        thread::sleep(METER_INTERVAL);
+        // The tee holds the only other reference
+        if shutdown.is_triggered() || Arc::strong_count(sent) == 1 {
+            return;
+        }
+
+        let count = sent.load(Ordering::Relaxed);
+        let elapsed = last_sample.elapsed().as_secs_f64();
+        #[allow(clippy::cast_precision_loss)]
+        let rate = (count - last_count) as f64 / elapsed;
+        info!(
+            "[ {} ] {rate:.1} packets/s ({count} sent so far)",
+            "Throughput Meter".green()
+        );
+
+        last_count = count;
+        last_sample = Instant::now();
+    }
+}
+
+/// Copy of the current stats
+pub fn snapshot(stats: &StatsTable) -> HashMap<NodeId, PacketStats> {
+    stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+}
+
+/// Stats as a text table, one drone per line sorted by id
+pub fn format_table(stats: &HashMap<NodeId, PacketStats>) -> String {
+    let mut ids: Vec<&NodeId> = stats.keys().collect();
+    ids.sort_unstable();
+
+    // `write!` on a String can't fail
+    let mut table = format!("{:>6} {:>10} {:>10}\n", "drone", "sent", "dropped");
+    for id in ids {
+        let PacketStats { sent, dropped, .. } = stats[id];
+        let _ = writeln!(table, "{id:>6} {sent:>10} {dropped:>10}");
+    }
+    table
+}
+
+/// Stats as CSV with a header line, one drone per line sorted by id: every
+/// drone of `drones`, with zeros for the silent ones, and any other drone in
+/// `stats`. Only drones report events, the kind is always `drone`.
+pub fn to_csv(drones: &[NodeId], stats: &HashMap<NodeId, PacketStats>) -> String {
+    let mut ids: BTreeSet<NodeId> = drones.iter().copied().collect();
+    ids.extend(stats.keys());
+
+    let mut csv = String::from("node_id,kind,sent,dropped,forwarded\n");
+    for id in ids {
+        let PacketStats {
+            sent,
+            dropped,
+            forwarded,
+        } = stats.get(&id).copied().unwrap_or_default();
+        let _ = writeln!(csv, "{id},drone,{sent},{dropped},{forwarded}");
+    }
+    csv
+}
+
+/// Packets of all the drones of `stats` together
+pub fn totals(stats: &HashMap<NodeId, PacketStats>) -> PacketStats {
+    stats
+        .values()
+        .fold(PacketStats::default(), |total, drone| PacketStats {
+            sent: total.sent + drone.sent,
+            dropped: total.dropped + drone.dropped,
+            forwarded: total.forwarded + drone.forwarded,
+        })
+}
+
+/// Mean and sample standard deviation of `values`, the deviation is 0 for a
+/// single value
+fn mean_stddev(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+/// Mean, standard deviation, min and max over `runs` of the totals of each
+/// run, as a text table with one line per counter
+pub fn format_aggregate(runs: &[PacketStats]) -> String {
+    let mut table = format!(
+        "{:>10} {:>12} {:>12} {:>10} {:>10}\n",
+        "packets", "mean", "stddev", "min", "max"
+    );
+    if runs.is_empty() {
+        return table;
+    }
+    let counters: [(&str, fn(&PacketStats) -> u64); 3] = [
+        ("sent", |run| run.sent),
+        ("dropped", |run| run.dropped),
+        ("forwarded", |run| run.forwarded),
+    ];
+    for (name, counter) in counters {
+        let values: Vec<u64> = runs.iter().map(counter).collect();
+        let as_f64: Vec<f64> = values.iter().map(|v| *v as f64).collect();
+        let (mean, stddev) = mean_stddev(&as_f64);
+        // `runs` isn't empty
+        let min = values.iter().min().copied().unwrap_or_default();
+        let max = values.iter().max().copied().unwrap_or_default();
+        let _ = writeln!(
+            table,
+            "{name:>10} {mean:>12.1} {stddev:>12.1} {min:>10} {max:>10}"
+        );
+    }
+    table
+}
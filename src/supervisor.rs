@@ -0,0 +1,128 @@
+//! Respawning drones whose thread panicked.
+//!
+//! A drone is moved into its thread, so once it panics nothing is left to
+//! run again. The supervisor keeps what's needed to build it anew: its config
+//! entry, its factory and clones of the channels handed to the factory. The
+//! channels are the same the rest of the network already holds, and the
+//! drone sends through the links of its delayed edges as it did at startup,
+//! so the new drone takes over the old one's place without rewiring
+//! anything.
+//!
+//! The retained channels also keep every node's packet channel and every
+//! link open, so the supervisor only lives as long as the drones it watches.
+
+use colored::Colorize;
+use crossbeam_channel::{Receiver, Sender};
+use log::{info, warn};
+use std::{collections::HashMap, sync::Arc, thread::JoinHandle, time::Duration};
+
+use wg_2024::{
+    config::Drone as ConfigDrone,
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+    packet::Packet,
+};
+
+use crate::{
+    error::{NetworkInitError, NodePanic},
+    link::LinkSenders,
+    network_initializer::{channel, spawn_named},
+    registry::DroneFactoryFn,
+    shutdown::Shutdown,
+};
+
+/// How often drone threads are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Everything needed to rebuild any drone of the network
+#[derive(Clone)]
+pub struct DroneSupervisor {
+    drones: HashMap<NodeId, (ConfigDrone, Arc<DroneFactoryFn>)>,
+    event_send: Sender<DroneEvent>,
+    command_recv: HashMap<NodeId, Receiver<DroneCommand>>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    link_send: LinkSenders,
+    packet_recv: HashMap<NodeId, Receiver<Packet>>,
+}
+
+impl DroneSupervisor {
+    pub fn new(
+        drones: HashMap<NodeId, (ConfigDrone, Arc<DroneFactoryFn>)>,
+        event_send: Sender<DroneEvent>,
+        command_recv: HashMap<NodeId, Receiver<DroneCommand>>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        link_send: LinkSenders,
+        packet_recv: HashMap<NodeId, Receiver<Packet>>,
+    ) -> Self {
+        Self {
+            drones,
+            event_send,
+            command_recv,
+            packet_send,
+            link_send,
+            packet_recv,
+        }
+    }
+
+    /// Build drone `id` again with its factory and channels and run it on a
+    /// new thread.
+    ///
+    /// The drone starts from its config entry: senders added or removed
+    /// through commands since the network started are not restored.
+    pub fn respawn_drone(&self, id: NodeId) -> Result<JoinHandle<()>, NetworkInitError> {
+        let (drone, factory) = channel(&self.drones, id, "Drone factory")?;
+        let mut drone = factory(
+            drone,
+            &self.event_send,
+            &self.command_recv,
+            &self.link_send.senders_of(id, &self.packet_send),
+            &self.packet_recv,
+        )?;
+
+        info!(
+            "[ {} ] Respawning [ Drone {id} ]",
+            "Network Initializer".green()
+        );
+        spawn_named(format!("drone-{id}"), move || drone.run())
+    }
+
+    /// Watch `drones` until they have all exited, respawning the ones that
+    /// panic unless `shutdown` was triggered. Returns every panic seen.
+    pub fn supervise(
+        self,
+        mut drones: Vec<(NodeId, JoinHandle<()>)>,
+        shutdown: &Shutdown,
+    ) -> Vec<NodePanic> {
+        let mut panics = Vec::new();
+
+        while !drones.is_empty() {
+            let (finished, running): (Vec<_>, Vec<_>) = drones
+                .into_iter()
+                .partition(|(_, handle)| handle.is_finished());
+            drones = running;
+
+            for (id, handle) in finished {
+                // A drone that returned was crashed on purpose
+                let Err(payload) = handle.join() else {
+                    continue;
+                };
+                panics.push(NodePanic::new(format!("drone {id}"), payload.as_ref()));
+
+                if shutdown.is_triggered() {
+                    continue;
+                }
+                match self.respawn_drone(id) {
+                    Ok(handle) => drones.push((id, handle)),
+                    Err(err) => warn!(
+                        "[ {} ] Unable to respawn [ Drone {id} ]: {err}",
+                        "Network Initializer".yellow()
+                    ),
+                }
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        panics
+    }
+}
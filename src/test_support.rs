@@ -0,0 +1,52 @@
+//! The smallest network that carries a message, for tests and examples.
+//!
+//! One client and one communication server, both next to a single drone
+//! that drops nothing. [`spawn_and_flood`] sends a flood from the client
+//! through the drone to the server and back, as
+//! [`verify_reachability`](crate::probe::verify_reachability) does. It
+//! checks the routes, no chat message is sent.
+
+use std::time::Duration;
+
+use wg_2024::network::NodeId;
+
+use crate::{
+    config::{ConfigBuilder, NetworkConfig, ServerKind},
+    error::NetworkInitError,
+    network_initializer::{Network, RunOptions},
+    probe,
+};
+
+pub const CLIENT: NodeId = 1;
+pub const DRONE: NodeId = 2;
+pub const SERVER: NodeId = 3;
+
+/// How long [`spawn_and_flood`] waits for the threads once it stopped them
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Config of the minimal network, to change before building it
+pub fn minimal_config() -> NetworkConfig {
+    ConfigBuilder::new()
+        .add_drone(DRONE, 0.0, &[CLIENT, SERVER])
+        .add_client(CLIENT, &[DRONE])
+        .add_server(SERVER, &[DRONE])
+        .server_kind(SERVER, ServerKind::Communication)
+        .build()
+}
+
+/// The minimal network, wired with the default implementations and ready
+/// to start
+pub fn minimal_network() -> Result<Network, NetworkInitError> {
+    Network::from_config(&minimal_config(), &RunOptions::default())
+}
+
+/// Start `network` headless, wait at most `timeout` for a flood from every
+/// client to reach every server, then stop it and join. The pairs that got
+/// no answer are reported as [`NetworkInitError::Unreachable`].
+pub fn spawn_and_flood(network: Network, timeout: Duration) -> Result<(), NetworkInitError> {
+    let running = network.start()?;
+    let reached = probe::verify_reachability(&running, timeout);
+    running.shutdown();
+    running.join_timeout(JOIN_TIMEOUT)?;
+    reached.map_err(NetworkInitError::Unreachable)
+}
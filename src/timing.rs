@@ -0,0 +1,60 @@
+//! How long the network took to start, phase by phase.
+
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+/// Duration of every startup phase, in the order they ran
+#[derive(Debug, Clone, Default)]
+pub struct StartupTimings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl StartupTimings {
+    /// Add a phase that took `duration`
+    pub fn record(&mut self, phase: &'static str, duration: Duration) {
+        self.phases.push((phase, duration));
+    }
+
+    /// Run `f` and record how long it took as `phase`
+    pub fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.record(phase, started.elapsed());
+        result
+    }
+
+    /// Add every phase of `other` after the ones already recorded
+    pub fn extend(&mut self, other: &Self) {
+        self.phases.extend_from_slice(&other.phases);
+    }
+
+    pub fn phases(&self) -> &[(&'static str, Duration)] {
+        &self.phases
+    }
+
+    /// Time spent in all the phases together
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// Phases as a text table, one per line in the order they ran
+    pub fn format_table(&self) -> String {
+        let width = self
+            .phases
+            .iter()
+            .map(|(phase, _)| phase.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+
+        // `write!` on a String can't fail
+        let mut table = String::new();
+        for (phase, duration) in &self.phases {
+            let _ = writeln!(table, "{phase:<width$} {duration:>12.3?}");
+        }
+        let _ = writeln!(table, "{:<width$} {:>12.3?}", "total", self.total());
+        table
+    }
+}
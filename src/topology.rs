@@ -0,0 +1,191 @@
+use crossbeam_channel::{Receiver, Select, Sender};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use wg_2024::{config::Config, controller::DroneCommand, network::NodeId};
+
+use crate::{
+    config::{self, NodeKind},
+    network_initializer::RunningNetwork,
+};
+
+/// Neighbors and kind of every node of the network
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Topology {
+    neighbors: HashMap<NodeId, Vec<NodeId>>,
+    kinds: HashMap<NodeId, NodeKind>,
+}
+
+/// Neighbor lists of every node of `config`, as the simulation controller
+/// and the connectivity checks expect them
+pub fn build_topology(config: &Config) -> Topology {
+    let mut topology = Topology::default();
+    for (kind, id, neighbors) in config::nodes(config) {
+        topology.neighbors.insert(id, neighbors.to_vec());
+        topology.kinds.insert(id, kind);
+    }
+    topology
+}
+
+impl Topology {
+    /// Neighbors of `id`, empty for an unknown node
+    pub fn neighbors(&self, id: NodeId) -> &[NodeId] {
+        self.neighbors.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Number of neighbors of `id`
+    pub fn degree(&self, id: NodeId) -> usize {
+        self.neighbors(id).len()
+    }
+
+    /// Kind of `id`, `None` for an unknown node
+    pub fn kind(&self, id: NodeId) -> Option<NodeKind> {
+        self.kinds.get(&id).copied()
+    }
+
+    /// Ids of every node of `kind`, sorted
+    pub fn nodes_of_kind(&self, kind: NodeKind) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = self
+            .kinds
+            .iter()
+            .filter(|(_, node_kind)| **node_kind == kind)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Ids of every node, sorted
+    pub fn nodes(&self) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = self.neighbors.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Whether every node can reach every other one, going through any node
+    pub fn is_connected(&self) -> bool {
+        let Some(&start) = self.neighbors.keys().next() else {
+            return true;
+        };
+
+        let mut reached = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            for &next in self.neighbors(node) {
+                if self.neighbors.contains_key(&next) && reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached.len() == self.neighbors.len()
+    }
+
+    /// The plain neighbor map
+    pub fn as_map(&self) -> &HashMap<NodeId, Vec<NodeId>> {
+        &self.neighbors
+    }
+
+    /// The plain neighbor map, as given to the simulation controller
+    pub fn into_map(self) -> HashMap<NodeId, Vec<NodeId>> {
+        self.neighbors
+    }
+}
+
+/// Topology of a running network, following the drone commands sent by
+/// [`crate::NodeCommands`], the fault schedule, the config reloader and the
+/// simulation controller, whose commands, e.g. from the GUI, go through
+/// [`LiveTopology::follow`]. The neighbors of a node are the nodes it holds
+/// a sender to. Drones the controller adds on its own get channels the
+/// initializer doesn't see.
+#[derive(Debug, Clone, Default)]
+pub struct LiveTopology(Arc<Mutex<Topology>>);
+
+impl LiveTopology {
+    pub(crate) fn new(topology: Topology) -> Self {
+        Self(Arc::new(Mutex::new(topology)))
+    }
+
+    /// Copy of the topology as it is now
+    pub fn current(&self) -> Topology {
+        self.0
+            .lock()
+            .map(|topology| topology.clone())
+            .unwrap_or_default()
+    }
+
+    /// Follow `command`, just sent to `drone`: a crashed drone leaves the
+    /// topology, its neighbors keep it until told to remove it
+    pub(crate) fn record(&self, drone: NodeId, command: &DroneCommand) {
+        let Ok(mut topology) = self.0.lock() else {
+            return;
+        };
+        match command {
+            DroneCommand::Crash => {
+                topology.neighbors.remove(&drone);
+                topology.kinds.remove(&drone);
+            }
+            DroneCommand::RemoveSender(neighbor) => {
+                if let Some(neighbors) = topology.neighbors.get_mut(&drone) {
+                    neighbors.retain(|n| n != neighbor);
+                }
+            }
+            DroneCommand::AddSender(neighbor, _) => {
+                if let Some(neighbors) = topology.neighbors.get_mut(&drone) {
+                    if !neighbors.contains(neighbor) {
+                        neighbors.push(*neighbor);
+                    }
+                }
+            }
+            DroneCommand::SetPacketDropRate(_) => {}
+        }
+    }
+
+    /// Forward every command received on a channel of `commands` to the
+    /// drone's own sender, recording it first. Returns once every receiver
+    /// is disconnected, i.e. once the controller is gone.
+    pub(crate) fn follow(
+        &self,
+        commands: &[(NodeId, Receiver<DroneCommand>, Sender<DroneCommand>)],
+    ) {
+        let mut select = Select::new();
+        for (_, command_recv, _) in commands {
+            select.recv(command_recv);
+        }
+
+        let mut open = commands.len();
+        while open > 0 {
+            let operation = select.select();
+            let index = operation.index();
+            let (drone, command_recv, command_send) = &commands[index];
+            match operation.recv(command_recv) {
+                Ok(command) => {
+                    self.record(*drone, &command);
+                    // A drone that already exited can't receive, that's fine
+                    let _ = command_send.send(command);
+                }
+                // A disconnected channel is always ready, stop selecting it
+                Err(_) => {
+                    select.remove(index);
+                    open -= 1;
+                }
+            }
+        }
+    }
+
+    /// Put `drone` in the topology, started with senders to `neighbors`
+    pub(crate) fn add_drone(&self, drone: NodeId, neighbors: &[NodeId]) {
+        if let Ok(mut topology) = self.0.lock() {
+            topology.neighbors.insert(drone, neighbors.to_vec());
+            topology.kinds.insert(drone, NodeKind::Drone);
+        }
+    }
+}
+
+/// Neighbors of every node of `network` as they are now, see
+/// [`LiveTopology`]. The REPL prints it with `topology`. The GUI has no
+/// button for it, since its `GUICommands` have no topology request.
+pub fn dump_topology(network: &RunningNetwork) -> Topology {
+    network.commands().topology()
+}
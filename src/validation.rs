@@ -0,0 +1,499 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use wg_2024::{config::Config, network::NodeId};
+
+use crate::{
+    config::{self, InitCommand, NetworkConfig, NodeKind},
+    topology::{build_topology, Topology},
+};
+
+/// Id kept out of configs by [`IdPolicy::NoReserved`]
+pub const RESERVED_ID: NodeId = NodeId::MAX;
+
+/// Constraints on the ids themselves, on top of being unique
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdPolicy {
+    /// Any `NodeId` is fine
+    #[default]
+    Permissive,
+    /// No node can use [`RESERVED_ID`]
+    NoReserved,
+    /// The ids must be exactly `0..n` for `n` nodes, so routing debug output
+    /// lines up with the positions in the config
+    Contiguous,
+}
+
+/// A single problem found in the config
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// `node` lists `neighbor`, but `neighbor` doesn't list `node` back
+    AsymmetricEdge {
+        kind: NodeKind,
+        node: NodeId,
+        neighbor: NodeId,
+    },
+    /// `node` lists itself as a neighbor
+    SelfLoop { kind: NodeKind, node: NodeId },
+    /// `id` is used by more than one node, `sections` lists each occurrence
+    DuplicateId { id: NodeId, sections: Vec<NodeKind> },
+    /// The packet drop rate of `drone` is not a number in `0.0..=1.0`
+    InvalidPdr { drone: NodeId, pdr: f32 },
+    /// `node` lists `neighbor`, which isn't defined anywhere in the config
+    UnknownNeighbor {
+        kind: NodeKind,
+        node: NodeId,
+        neighbor: NodeId,
+    },
+    /// A client or server lists a neighbor that is a client or server
+    NeighborNotDrone {
+        kind: NodeKind,
+        node: NodeId,
+        neighbor: NodeId,
+        neighbor_kind: NodeKind,
+    },
+    /// A client doesn't have 1 or 2 drone connections, or a server has fewer
+    /// than 2, as the protocol requires
+    NeighborCount {
+        kind: NodeKind,
+        node: NodeId,
+        count: usize,
+    },
+    /// No client can reach `node` going through drones
+    Unreachable { kind: NodeKind, node: NodeId },
+    /// `node` has no neighbors at all
+    Isolated { kind: NodeKind, node: NodeId },
+    /// `node` uses [`RESERVED_ID`]
+    ReservedId { kind: NodeKind, node: NodeId },
+    /// Ids must be contiguous from 0, but no node uses `id`
+    MissingId { id: NodeId },
+    /// There are clients or servers but no drone to connect them
+    NoDrones { clients: usize, servers: usize },
+    /// A `[[fault]]` entry targets `node`, which isn't a drone
+    FaultNotDrone { node: NodeId },
+    /// A `[[fault]]` entry restores `drone` before crashing it
+    RestoreBeforeCrash { drone: NodeId },
+    /// An init command of `drone` adds or removes `node`, which is the
+    /// drone itself or isn't defined in the config
+    InitCommandNode { drone: NodeId, node: NodeId },
+    /// An init command of `drone` sets a pdr outside [0.0, 1.0]
+    InitCommandPdr { drone: NodeId, pdr: f32 },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AsymmetricEdge {
+                kind,
+                node,
+                neighbor,
+            } => write!(
+                f,
+                "{kind} {node} connects to {neighbor} but {neighbor} does not connect back to {node}"
+            ),
+            Self::SelfLoop { kind, node } => write!(f, "{kind} {node} lists itself as a neighbor"),
+            Self::DuplicateId { id, sections } => {
+                let sections: Vec<String> = sections.iter().map(ToString::to_string).collect();
+                write!(f, "id {id} is used more than once: {}", sections.join(", "))
+            }
+            Self::InvalidPdr { drone, pdr } => {
+                write!(f, "drone {drone} has pdr {pdr}, expected a value in [0.0, 1.0]")
+            }
+            Self::UnknownNeighbor {
+                kind,
+                node,
+                neighbor,
+            } => write!(
+                f,
+                "{kind} {node} connects to {neighbor} which is not defined in the config"
+            ),
+            Self::NeighborNotDrone {
+                kind,
+                node,
+                neighbor,
+                neighbor_kind,
+            } => write!(
+                f,
+                "{kind} {node} connects to {neighbor} which is a {neighbor_kind}, {kind}s can only connect to drones"
+            ),
+            Self::NeighborCount { kind, node, count } => {
+                let expected = match kind {
+                    NodeKind::Client => "1 or 2",
+                    _ => "at least 2",
+                };
+                write!(
+                    f,
+                    "{kind} {node} has {count} drone connection(s), expected {expected}"
+                )
+            }
+            Self::Unreachable { kind, node } => {
+                write!(f, "{kind} {node} can't be reached from any client")
+            }
+            Self::Isolated { kind, node } => write!(f, "{kind} {node} has no neighbors"),
+            Self::ReservedId { kind, node } => write!(f, "{kind} {node} uses a reserved id"),
+            Self::MissingId { id } => {
+                write!(f, "ids must be contiguous from 0 but no node uses id {id}")
+            }
+            Self::FaultNotDrone { node } => {
+                write!(f, "fault targets {node} which is not a drone")
+            }
+            Self::RestoreBeforeCrash { drone } => {
+                write!(f, "fault restores drone {drone} before crashing it")
+            }
+            Self::InitCommandNode { drone, node } if drone == node => {
+                write!(f, "an init command of drone {drone} adds or removes itself")
+            }
+            Self::InitCommandNode { drone, node } => write!(
+                f,
+                "an init command of drone {drone} targets {node} which is not defined in the config"
+            ),
+            Self::InitCommandPdr { drone, pdr } => write!(
+                f,
+                "an init command of drone {drone} sets pdr {pdr}, expected a value in [0.0, 1.0]"
+            ),
+            Self::NoDrones { clients, servers } => write!(
+                f,
+                "the config has {clients} client(s) and {servers} server(s) but no drone to connect them"
+            ),
+        }
+    }
+}
+
+/// Run every check on `config`, collecting all the problems found
+pub fn validate(config: &Config) -> Result<(), Vec<ValidationError>> {
+    // Every other check would only repeat it for each client and server
+    validate_has_drones(config)?;
+
+    let errors: Vec<ValidationError> = CONFIG_CHECKS
+        .iter()
+        .filter_map(|check| check(config).err())
+        .flatten()
+        .collect();
+
+    report(errors)
+}
+
+/// The checks of [`validate`], in order, also the first [`lint_checks`]
+const CONFIG_CHECKS: [fn(&Config) -> Result<(), Vec<ValidationError>>; 7] = [
+    validate_unique_ids,
+    validate_topology,
+    validate_neighbors_exist,
+    validate_no_self_loops,
+    validate_pdr,
+    validate_endpoint_neighbors,
+    validate_neighbor_count,
+];
+
+/// One of the checks run by [`lint`]
+pub type Check = Box<dyn Fn(&NetworkConfig) -> Result<(), Vec<ValidationError>>>;
+
+/// Every check [`lint`] runs, in order: those of [`validate`], the ids
+/// against `policy`, the faults, the init commands and the connectivity
+pub fn lint_checks(policy: IdPolicy) -> Vec<Check> {
+    let mut checks: Vec<Check> = CONFIG_CHECKS
+        .into_iter()
+        .map(|check| Box::new(move |c: &NetworkConfig| check(&c.config)) as Check)
+        .collect();
+    checks.push(Box::new(move |c: &NetworkConfig| {
+        validate_id_space(&c.config, policy)
+    }));
+    checks.push(Box::new(validate_faults));
+    checks.push(Box::new(validate_init_commands));
+    checks.push(Box::new(|c: &NetworkConfig| {
+        validate_connectivity(&build_topology(&c.config))
+    }));
+    checks
+}
+
+/// Every problem found in `network_config` by the [`lint_checks`], without
+/// building anything. Unlike the startup checks, an unreachable or isolated
+/// node is always a problem.
+pub fn lint(network_config: &NetworkConfig, policy: IdPolicy) -> Vec<ValidationError> {
+    // Same as in `validate`, the other checks would only repeat it
+    if let Err(errors) = validate_has_drones(&network_config.config) {
+        return errors;
+    }
+    lint_checks(policy)
+        .iter()
+        .filter_map(|check| check(network_config).err())
+        .flatten()
+        .collect()
+}
+
+/// Check that a config with clients or servers has drones to connect them
+pub fn validate_has_drones(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let (clients, servers) = (config.client.len(), config.server.len());
+    if config.drone.is_empty() && clients + servers > 0 {
+        return Err(vec![ValidationError::NoDrones { clients, servers }]);
+    }
+    Ok(())
+}
+
+/// Check that no id is shared by two nodes, in the same section or across sections
+pub fn validate_unique_ids(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let mut order = Vec::new();
+    let mut sections = HashMap::<NodeId, Vec<NodeKind>>::new();
+    for (kind, id, _) in config::nodes(config) {
+        let entry = sections.entry(id).or_default();
+        if entry.is_empty() {
+            order.push(id);
+        }
+        entry.push(kind);
+    }
+
+    let errors: Vec<ValidationError> = order
+        .into_iter()
+        .filter_map(|id| {
+            let sections = sections.remove(&id)?;
+            (sections.len() > 1).then_some(ValidationError::DuplicateId { id, sections })
+        })
+        .collect();
+
+    report(errors)
+}
+
+/// Check that every edge is listed by both of its ends
+pub fn validate_topology(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let adjacency: HashMap<NodeId, &[NodeId]> = config::nodes(config)
+        .map(|(_, id, neighbors)| (id, neighbors))
+        .collect();
+
+    let mut errors = Vec::new();
+    for (kind, node, neighbors) in config::nodes(config) {
+        for &neighbor in neighbors {
+            // An unknown neighbor is a different problem, not an asymmetry
+            if let Some(back) = adjacency.get(&neighbor) {
+                if !back.contains(&node) {
+                    errors.push(ValidationError::AsymmetricEdge {
+                        kind,
+                        node,
+                        neighbor,
+                    });
+                }
+            }
+        }
+    }
+
+    report(errors)
+}
+
+/// Check that no node lists itself as a neighbor
+pub fn validate_no_self_loops(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let errors = config::nodes(config)
+        .filter(|(_, id, neighbors)| neighbors.contains(id))
+        .map(|(kind, node, _)| ValidationError::SelfLoop { kind, node })
+        .collect();
+
+    report(errors)
+}
+
+/// Check that every drone has a finite packet drop rate between 0 and 1
+pub fn validate_pdr(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let errors = config
+        .drone
+        .iter()
+        // NaN is never contained in the range
+        .filter(|drone| !(0.0..=1.0).contains(&drone.pdr))
+        .map(|drone| ValidationError::InvalidPdr {
+            drone: drone.id,
+            pdr: drone.pdr,
+        })
+        .collect();
+
+    report(errors)
+}
+
+/// Check that every neighbor of every node is defined in the config
+pub fn validate_neighbors_exist(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let defined: HashSet<NodeId> = config::nodes(config).map(|(_, id, _)| id).collect();
+
+    let errors = config::nodes(config)
+        .flat_map(|(kind, node, neighbors)| {
+            neighbors
+                .iter()
+                .filter(|neighbor| !defined.contains(neighbor))
+                .map(move |&neighbor| ValidationError::UnknownNeighbor {
+                    kind,
+                    node,
+                    neighbor,
+                })
+        })
+        .collect();
+
+    report(errors)
+}
+
+/// Check that clients and servers only connect to drones, undefined
+/// neighbors are left to [`validate_neighbors_exist`]
+pub fn validate_endpoint_neighbors(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let kinds: HashMap<NodeId, NodeKind> = config::nodes(config)
+        .map(|(kind, id, _)| (id, kind))
+        .collect();
+
+    let mut errors = Vec::new();
+    for (kind, node, neighbors) in config::nodes(config) {
+        if kind == NodeKind::Drone {
+            continue;
+        }
+        for &neighbor in neighbors {
+            let Some(&neighbor_kind) = kinds.get(&neighbor) else {
+                continue;
+            };
+            if neighbor_kind != NodeKind::Drone {
+                errors.push(ValidationError::NeighborNotDrone {
+                    kind,
+                    node,
+                    neighbor,
+                    neighbor_kind,
+                });
+            }
+        }
+    }
+
+    report(errors)
+}
+
+/// Check that clients have 1 or 2 drone connections and servers at least 2
+pub fn validate_neighbor_count(config: &Config) -> Result<(), Vec<ValidationError>> {
+    let clients = config
+        .client
+        .iter()
+        .map(|c| (NodeKind::Client, c.id, c.connected_drone_ids.len()))
+        .filter(|(_, _, count)| !(1..=2).contains(count));
+    let servers = config
+        .server
+        .iter()
+        .map(|s| (NodeKind::Server, s.id, s.connected_drone_ids.len()))
+        .filter(|(_, _, count)| *count < 2);
+
+    let errors = clients
+        .chain(servers)
+        .map(|(kind, node, count)| ValidationError::NeighborCount { kind, node, count })
+        .collect();
+
+    report(errors)
+}
+
+/// Check that every `[[fault]]` entry crashes a drone and restores it only
+/// after crashing it
+pub fn validate_faults(network_config: &NetworkConfig) -> Result<(), Vec<ValidationError>> {
+    let drones: HashSet<NodeId> = network_config.config.drone.iter().map(|d| d.id).collect();
+    let mut errors = Vec::new();
+    for fault in &network_config.faults {
+        if !drones.contains(&fault.drone) {
+            errors.push(ValidationError::FaultNotDrone { node: fault.drone });
+        } else if fault
+            .restore_at
+            .is_some_and(|restore_at| restore_at <= fault.crash_at)
+        {
+            errors.push(ValidationError::RestoreBeforeCrash { drone: fault.drone });
+        }
+    }
+    report(errors)
+}
+
+/// Check that the init commands of every drone set a valid pdr and target
+/// another node of the config
+pub fn validate_init_commands(network_config: &NetworkConfig) -> Result<(), Vec<ValidationError>> {
+    let ids: HashSet<NodeId> = config::nodes(&network_config.config)
+        .map(|(_, id, _)| id)
+        .collect();
+    let mut errors = Vec::new();
+    // In config order, so the errors are too
+    for drone in &network_config.config.drone {
+        let Some(commands) = network_config.init_commands.get(&drone.id) else {
+            continue;
+        };
+        for command in commands {
+            if let InitCommand::SetPdr { pdr } = command {
+                if !(0.0..=1.0).contains(pdr) {
+                    errors.push(ValidationError::InitCommandPdr {
+                        drone: drone.id,
+                        pdr: *pdr,
+                    });
+                }
+            }
+            if let Some(node) = command.node() {
+                if node == drone.id || !ids.contains(&node) {
+                    errors.push(ValidationError::InitCommandNode {
+                        drone: drone.id,
+                        node,
+                    });
+                }
+            }
+        }
+    }
+    report(errors)
+}
+
+/// Check that the ids follow `policy`
+pub fn validate_id_space(config: &Config, policy: IdPolicy) -> Result<(), Vec<ValidationError>> {
+    let errors = match policy {
+        IdPolicy::Permissive => Vec::new(),
+        IdPolicy::NoReserved => config::nodes(config)
+            .filter(|(_, id, _)| *id == RESERVED_ID)
+            .map(|(kind, node, _)| ValidationError::ReservedId { kind, node })
+            .collect(),
+        IdPolicy::Contiguous => {
+            let used: HashSet<NodeId> = config::nodes(config).map(|(_, id, _)| id).collect();
+            // Every id below the highest one must be used, duplicates are
+            // reported by `validate_unique_ids`
+            match used.iter().max() {
+                Some(&max) => (0..max)
+                    .filter(|id| !used.contains(id))
+                    .map(|id| ValidationError::MissingId { id })
+                    .collect(),
+                None => Vec::new(),
+            }
+        }
+    };
+
+    report(errors)
+}
+
+/// Check that every node can be reached from at least one client and that no
+/// node is isolated.
+///
+/// Works on the topology built for the simulation controller. Only drones
+/// forward packets, so the search never goes through a client or a server.
+/// Without clients there's nothing to reach from, so only isolated nodes are
+/// reported.
+pub fn validate_connectivity(topology: &Topology) -> Result<(), Vec<ValidationError>> {
+    let clients = topology.nodes_of_kind(NodeKind::Client);
+
+    // Breadth first search starting from every client at once
+    let mut reached: HashSet<NodeId> = clients.iter().copied().collect();
+    let mut queue: VecDeque<NodeId> = clients.iter().copied().collect();
+    while let Some(node) = queue.pop_front() {
+        for &next in topology.neighbors(node) {
+            if reached.insert(next) && topology.kind(next) == Some(NodeKind::Drone) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut errors = Vec::new();
+    for node in topology.nodes() {
+        let Some(kind) = topology.kind(node) else {
+            continue;
+        };
+        if topology.degree(node) == 0 {
+            errors.push(ValidationError::Isolated { kind, node });
+        } else if !clients.is_empty() && !reached.contains(&node) {
+            errors.push(ValidationError::Unreachable { kind, node });
+        }
+    }
+
+    report(errors)
+}
+
+/// Turn the problems found by a check into its result
+fn report(errors: Vec<ValidationError>) -> Result<(), Vec<ValidationError>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
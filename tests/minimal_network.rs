@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use network_initializer::test_support::{minimal_network, spawn_and_flood};
+
+#[test]
+fn minimal_network_starts_and_stops() {
+    let network = minimal_network().expect("the minimal network builds");
+    let running = network.start().expect("the minimal network starts");
+    running.shutdown();
+    running
+        .join_timeout(Duration::from_secs(5))
+        .expect("every node thread ends");
+}
+
+#[test]
+#[ignore = "waits for the flood to go through the real node implementations, run with --ignored"]
+fn minimal_network_carries_a_flood() {
+    let network = minimal_network().expect("the minimal network builds");
+    spawn_and_flood(network, Duration::from_secs(5)).expect("the server answers the client");
+}